@@ -0,0 +1,105 @@
+//! Compare the inline capacities of the event batches on a large multi-monitor wall.
+//!
+//! Every display of the wall reports an `OriginChanged` event, as when the
+//! primary display moves. The batch used to keep 4 (macOS) or 10 (Windows)
+//! events inline, and now keeps 16.
+//!
+//! Run with `cargo bench --bench event_batch`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use display_config::{Display, DisplayRotation, Event, InputCapabilities};
+use dpi::{LogicalPosition, LogicalSize};
+use smallvec::{Array, SmallVec};
+
+/// The number of displays of the wall.
+const DISPLAYS: u32 = 16;
+
+/// How many batches are collected for each capacity.
+const ITERATIONS: u32 = 100_000;
+
+/// An allocator that counts the allocations, to see which batches spill to the heap.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// A display of the wall. Cloning it doesn't allocate, so only the batches do.
+fn display(index: u32) -> Display {
+    #[cfg(target_os = "windows")]
+    let id = display_config::windows::WindowsDisplayId::new(format!(r"\\.\DISPLAY{index}").into());
+    #[cfg(target_os = "macos")]
+    let id = index;
+
+    Display {
+        id: id.into(),
+        origin: LogicalPosition::new(index as i32 * 1920, 0),
+        size: LogicalSize::new(1920, 1080),
+        scale_factor: 1.0,
+        is_primary: index == 0,
+        is_mirrored: false,
+        mirror_source: None,
+        is_builtin: false,
+        is_virtual: false,
+        rotation: DisplayRotation::Deg0,
+        refresh_rate: Some(60.0),
+        vrr: None,
+        subpixel: None,
+        input_capabilities: InputCapabilities::default(),
+    }
+}
+
+/// Collect the events of one reconfiguration of the wall into a batch.
+fn collect<A: Array<Item = Event>>(displays: &[Display]) -> SmallVec<A> {
+    displays
+        .iter()
+        .map(|display| Event::OriginChanged {
+            display: display.clone(),
+            before: display.origin,
+            after: LogicalPosition::new(display.origin.x - 1920, display.origin.y),
+            caused_by_primary_shift: true,
+        })
+        .collect()
+}
+
+fn bench<A: Array<Item = Event>>(name: &str, displays: &[Display]) {
+    let start_allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(collect::<A>(black_box(displays)));
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - start_allocations;
+
+    println!(
+        "{name:>12}: {:>8.1?} per batch, {} heap allocations per batch",
+        elapsed / ITERATIONS,
+        allocations as u32 / ITERATIONS,
+    );
+}
+
+fn main() {
+    let displays: Vec<_> = (0..DISPLAYS).map(display).collect();
+
+    bench::<[Event; 4]>("inline 4", &displays);
+    bench::<[Event; 10]>("inline 10", &displays);
+    bench::<[Event; 16]>("inline 16", &displays);
+}
@@ -0,0 +1,23 @@
+use display_config::DisplayObserver;
+
+fn main() {
+    #[cfg(target_os = "windows")]
+    display_config::windows::set_process_per_monitor_dpi_aware()
+        .expect("Failed to set process as DPI aware");
+
+    let observer = DisplayObserver::new().expect("Failed to create the observer");
+    let stop_token = observer.stop_token();
+    let mut count = 0;
+
+    observer.set_callback(move |event| {
+        println!("{event:#?}");
+
+        count += 1;
+        if count == 5 {
+            stop_token.stop();
+        }
+    });
+
+    observer.run().expect("Failed to run the application");
+    println!("Stopped after 5 events.");
+}
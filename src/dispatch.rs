@@ -0,0 +1,672 @@
+//! This module contains the platform-independent part of the event dispatching,
+//! which is shared by the platform-specific display observers.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex, MutexGuard, PoisonError,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+#[cfg(feature = "async")]
+use std::{
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+use crate::{
+    ChangeReason, DisplayChangeCallback, DisplayEventCallback, DisplayEventMapper, DisplayId,
+    Event, EventBatch, invalidate_displays_cache, report_platform_event, tracking::coalesce_events,
+};
+
+/// Lock the state shared between an observer and its platform callbacks.
+///
+/// A panicking callback poisons the mutex while the events are dispatched. The
+/// state is still consistent, since the panic can only interrupt the callback
+/// itself, so the poison is cleared to keep dispatching the following events.
+pub(crate) fn lock_state<T>(state: &Mutex<T>) -> MutexGuard<'_, T> {
+    state.lock().unwrap_or_else(|poisoned| {
+        state.clear_poison();
+        PoisonError::into_inner(poisoned)
+    })
+}
+
+/// The kind of an [`Event`], used to look up the handler of a typed subscription.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum EventKind {
+    Added,
+    Removed,
+    SizeChanged,
+    OriginChanged,
+    Mirrored,
+    UnMirrored,
+    ColorProfileChanged,
+    Reconnected,
+    MirrorSourceChanged,
+    VirtualOriginChanged,
+    AllDisplaysRemoved,
+    DisplaysRestored,
+    PrimaryChanged,
+    RefreshRateChanged,
+    RotationChanged,
+}
+
+impl EventKind {
+    fn of(event: &Event) -> Self {
+        match event {
+            Event::Added { .. } => Self::Added,
+            Event::Removed { .. } => Self::Removed,
+            Event::SizeChanged { .. } => Self::SizeChanged,
+            Event::OriginChanged { .. } => Self::OriginChanged,
+            Event::Mirrored(_) => Self::Mirrored,
+            Event::UnMirrored(_) => Self::UnMirrored,
+            Event::ColorProfileChanged { .. } => Self::ColorProfileChanged,
+            Event::Reconnected { .. } => Self::Reconnected,
+            Event::MirrorSourceChanged { .. } => Self::MirrorSourceChanged,
+            Event::VirtualOriginChanged { .. } => Self::VirtualOriginChanged,
+            Event::AllDisplaysRemoved => Self::AllDisplaysRemoved,
+            Event::DisplaysRestored => Self::DisplaysRestored,
+            Event::PrimaryChanged { .. } => Self::PrimaryChanged,
+            Event::RefreshRateChanged { .. } => Self::RefreshRateChanged,
+            Event::RotationChanged { .. } => Self::RotationChanged,
+        }
+    }
+}
+
+/// The handlers that receive the events tracked by a platform-specific observer.
+#[derive(Default)]
+pub(crate) struct EventDispatcher {
+    callback: Option<DisplayEventCallback>,
+    /// The handlers of the typed subscriptions, which receive only one kind of event.
+    handlers: HashMap<EventKind, DisplayEventCallback>,
+    mapper: Option<DisplayEventMapper>,
+    change_callback: Option<DisplayChangeCallback>,
+    /// Whether a batch was delivered since the change callback was last called.
+    change_pending: bool,
+    /// Whether the calls of the change callback are held back until
+    /// [`EventDispatcher::flush_change_callback`].
+    change_callback_deferred: bool,
+    /// The number of events left to pass to the callback, and the function
+    /// called once none are left.
+    limit: Option<(usize, Box<dyn FnMut() + Send>)>,
+    /// The number of reconfigurations dispatched so far.
+    ///
+    /// This is shared with the observer, so it can be read without locking the
+    /// dispatcher, e.g. from inside a callback.
+    generation: Arc<AtomicU64>,
+    /// How long a removal is held back to see whether the display comes back,
+    /// or `None` if removals are passed on right away.
+    reconnect_window: Option<Duration>,
+    /// The removals held back, with the reason and the time they happened.
+    pending_removals: Vec<(DisplayId, ChangeReason, Instant)>,
+    /// Whether [`Event::AllDisplaysRemoved`] is held back until the removals
+    /// held back are delivered, so that it still follows them.
+    all_displays_removed_pending: bool,
+    /// The events held back while the dispatch is suspended, or `None` if it isn't.
+    suspended_events: Option<Vec<Event>>,
+    /// Whether the events are also written to the log of the OS.
+    log_to_os: bool,
+    /// The events delivered since the observer was last polled, or `None` if
+    /// it was never polled.
+    polled_events: Option<Vec<Event>>,
+    /// The slots of the [`NextEvent`] futures waiting for the next event.
+    #[cfg(feature = "async")]
+    next_event_slots: Vec<Arc<Mutex<NextEventSlot>>>,
+}
+
+/// The state shared between a [`NextEvent`] future and the dispatcher.
+#[cfg(feature = "async")]
+#[derive(Default)]
+struct NextEventSlot {
+    event: Option<Event>,
+    /// Whether the dispatcher or the future is gone, so the slot is no longer used.
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+/// A future that resolves on the next event passed to the callback, or `None`
+/// once the observer is dropped.
+#[cfg(feature = "async")]
+pub(crate) struct NextEvent {
+    slot: Arc<Mutex<NextEventSlot>>,
+}
+
+#[cfg(feature = "async")]
+impl Drop for NextEvent {
+    fn drop(&mut self) {
+        // Let the dispatcher forget the slot, even if no event comes anymore.
+        lock_state(&self.slot).closed = true;
+    }
+}
+
+#[cfg(feature = "async")]
+impl Future for NextEvent {
+    type Output = Option<Event>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = lock_state(&self.slot);
+
+        if let Some(event) = slot.event.take() {
+            Poll::Ready(Some(event))
+        } else if slot.closed {
+            Poll::Ready(None)
+        } else {
+            slot.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl EventDispatcher {
+    /// Get the counter of dispatched reconfigurations.
+    pub(crate) fn generation(&self) -> Arc<AtomicU64> {
+        Arc::clone(&self.generation)
+    }
+
+    pub(crate) fn set_callback(&mut self, callback: DisplayEventCallback) {
+        self.callback = Some(callback);
+    }
+
+    pub(crate) fn remove_callback(&mut self) -> bool {
+        self.callback.take().is_some()
+    }
+
+    pub(crate) fn set_handler(&mut self, kind: EventKind, handler: DisplayEventCallback) {
+        self.handlers.insert(kind, handler);
+    }
+
+    pub(crate) fn set_mapper(&mut self, mapper: DisplayEventMapper) {
+        self.mapper = Some(mapper);
+    }
+
+    pub(crate) fn remove_mapper(&mut self) -> bool {
+        self.mapper.take().is_some()
+    }
+
+    pub(crate) fn set_change_callback(&mut self, callback: DisplayChangeCallback) {
+        self.change_callback = Some(callback);
+    }
+
+    /// Hold back the calls of the change callback until
+    /// [`EventDispatcher::flush_change_callback`], so that the batches
+    /// delivered meanwhile call it once.
+    ///
+    /// Returns `true` if they weren't held back already, so the caller has to flush them.
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    pub(crate) fn defer_change_callback(&mut self) -> bool {
+        !std::mem::replace(&mut self.change_callback_deferred, true)
+    }
+
+    /// Call the change callback held back, if a batch was delivered meanwhile.
+    pub(crate) fn flush_change_callback(&mut self) {
+        self.change_callback_deferred = false;
+
+        if std::mem::take(&mut self.change_pending)
+            && let Some(change_callback) = self.change_callback.as_mut()
+        {
+            (change_callback)();
+        }
+    }
+
+    pub(crate) fn set_limit(&mut self, count: usize, on_reached: Box<dyn FnMut() + Send>) {
+        self.limit = Some((count, on_reached));
+    }
+
+    pub(crate) fn remove_limit(&mut self) {
+        self.limit = None;
+    }
+
+    /// Get a future resolving on the next event.
+    ///
+    /// The slot of the future is only kept until the next event or until the
+    /// future is dropped, so nothing stays installed once the future is done.
+    #[cfg(feature = "async")]
+    pub(crate) fn next_event(&mut self) -> NextEvent {
+        self.prune_next_event_slots();
+
+        let slot = Arc::new(Mutex::new(NextEventSlot::default()));
+        self.next_event_slots.push(Arc::clone(&slot));
+        NextEvent { slot }
+    }
+
+    /// Forget the slots of the futures dropped before an event came.
+    #[cfg(feature = "async")]
+    fn prune_next_event_slots(&mut self) {
+        self.next_event_slots
+            .retain(|slot| !lock_state(slot).closed);
+    }
+
+    /// Pass the event to the futures waiting for it.
+    #[cfg(feature = "async")]
+    fn fill_next_event_slots(&mut self, event: &Event) {
+        for slot in self.next_event_slots.drain(..) {
+            let mut slot = lock_state(&slot);
+            slot.event = Some(event.clone());
+            if let Some(waker) = slot.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+
+    /// Start collecting the delivered events for [`EventDispatcher::take_polled_events`],
+    /// unless they're collected already.
+    ///
+    /// They're collected from then on, since the event loop of the app may
+    /// handle the platform messages before the observer is polled again.
+    pub(crate) fn start_polling(&mut self) {
+        self.polled_events.get_or_insert_with(Vec::new);
+    }
+
+    /// Get the events collected since the last call.
+    pub(crate) fn take_polled_events(&mut self) -> Vec<Event> {
+        self.polled_events
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn set_log_to_os(&mut self, enabled: bool) {
+        self.log_to_os = enabled;
+    }
+
+    /// Set how long removals are held back to be coalesced with an addition
+    /// of the same display into [`Event::Reconnected`].
+    ///
+    /// Disabling it delivers the removals held back so far.
+    pub(crate) fn set_reconnect_window(&mut self, window: Option<Duration>) {
+        self.reconnect_window = window;
+
+        if window.is_none() {
+            let mut removals = self
+                .pending_removals
+                .drain(..)
+                .map(|(id, reason, _)| Event::Removed { id, reason })
+                .collect();
+            self.release_all_displays_removed(&mut removals);
+            self.deliver(removals);
+        }
+    }
+
+    /// Get when the next removal held back is due to be delivered.
+    pub(crate) fn next_removal_deadline(&self) -> Option<Instant> {
+        let window = self.reconnect_window?;
+        self.pending_removals
+            .iter()
+            .map(|(_, _, removed_at)| *removed_at + window)
+            .min()
+    }
+
+    /// Deliver the removals whose display didn't come back within the window.
+    pub(crate) fn flush_removals(&mut self) {
+        let Some(window) = self.reconnect_window else {
+            return;
+        };
+
+        let now = Instant::now();
+        let mut removals = EventBatch::new();
+        self.pending_removals.retain(|(id, reason, removed_at)| {
+            let expired = now.duration_since(*removed_at) >= window;
+            if expired {
+                removals.push(Event::Removed {
+                    id: id.clone(),
+                    reason: *reason,
+                });
+            }
+            !expired
+        });
+
+        self.release_all_displays_removed(&mut removals);
+        self.deliver(removals);
+    }
+
+    /// Add the [`Event::AllDisplaysRemoved`] held back after the removals, once
+    /// none of them is held back anymore.
+    fn release_all_displays_removed(&mut self, removals: &mut EventBatch) {
+        if self.pending_removals.is_empty()
+            && std::mem::take(&mut self.all_displays_removed_pending)
+        {
+            removals.push(Event::AllDisplaysRemoved);
+        }
+    }
+
+    /// Hold back the events until [`EventDispatcher::resume`] is called.
+    pub(crate) fn suspend(&mut self) {
+        self.suspended_events.get_or_insert_with(Vec::new);
+    }
+
+    /// Deliver the net change of the events held back since [`EventDispatcher::suspend`].
+    pub(crate) fn resume(&mut self) {
+        if let Some(events) = self.suspended_events.take() {
+            self.deliver(coalesce_events(events));
+        }
+    }
+
+    /// Dispatch the events of a single reconfiguration.
+    pub(crate) fn dispatch(&mut self, events: EventBatch) {
+        if events.is_empty() {
+            return;
+        }
+
+        invalidate_displays_cache();
+        self.generation.fetch_add(1, Ordering::Release);
+
+        let events = if self.reconnect_window.is_some() {
+            self.coalesce_reconnections(events)
+        } else {
+            events
+        };
+
+        self.deliver(events);
+    }
+
+    /// Hold back the removals, and turn the additions of displays removed
+    /// within the window into [`Event::Reconnected`].
+    ///
+    /// [`Event::AllDisplaysRemoved`] is held back with the removals, and dropped
+    /// together with [`Event::DisplaysRestored`] if a display is added before
+    /// they are delivered.
+    fn coalesce_reconnections(&mut self, events: EventBatch) -> EventBatch {
+        let now = Instant::now();
+
+        events
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::Removed { id, reason } => {
+                    self.pending_removals.push((id, reason, now));
+                    None
+                }
+                Event::Added { display, reason } => {
+                    let pending = self
+                        .pending_removals
+                        .iter()
+                        .position(|(id, _, _)| *id == display.id);
+
+                    Some(match pending {
+                        Some(index) => {
+                            self.pending_removals.remove(index);
+                            Event::Reconnected { display }
+                        }
+                        None => Event::Added { display, reason },
+                    })
+                }
+                Event::AllDisplaysRemoved if !self.pending_removals.is_empty() => {
+                    self.all_displays_removed_pending = true;
+                    None
+                }
+                // The displays came back before they were reported gone.
+                Event::DisplaysRestored if self.all_displays_removed_pending => {
+                    self.all_displays_removed_pending = false;
+                    None
+                }
+                event => Some(event),
+            })
+            .collect()
+    }
+
+    /// Pass the events to the handlers and the callback.
+    fn deliver(&mut self, events: EventBatch) {
+        if let Some(suspended_events) = self.suspended_events.as_mut() {
+            suspended_events.extend(events);
+            return;
+        }
+
+        if self.log_to_os {
+            events.iter().for_each(report_platform_event);
+        }
+
+        #[cfg(feature = "async")]
+        let has_next_event_slots = {
+            self.prune_next_event_slots();
+            !self.next_event_slots.is_empty()
+        };
+        #[cfg(not(feature = "async"))]
+        let has_next_event_slots = false;
+
+        if self.callback.is_none()
+            && self.handlers.is_empty()
+            && self.change_callback.is_none()
+            && !has_next_event_slots
+            && self.polled_events.is_none()
+        {
+            return;
+        }
+
+        let mut delivered = false;
+        for event in events {
+            if matches!(self.limit, Some((0, _))) {
+                break;
+            }
+
+            let event = match self.mapper.as_mut() {
+                Some(mapper) => match (mapper)(event) {
+                    Some(event) => event,
+                    None => continue,
+                },
+                None => event,
+            };
+            delivered = true;
+
+            #[cfg(feature = "async")]
+            self.fill_next_event_slots(&event);
+
+            if let Some(polled_events) = self.polled_events.as_mut() {
+                polled_events.push(event.clone());
+            }
+
+            if let Some(handler) = self.handlers.get_mut(&EventKind::of(&event)) {
+                (handler)(event.clone());
+            }
+
+            if let Some(callback) = self.callback.as_mut() {
+                (callback)(event);
+            }
+
+            if let Some((remaining, on_reached)) = self.limit.as_mut() {
+                *remaining -= 1;
+                if *remaining == 0 {
+                    (on_reached)();
+                }
+            }
+        }
+
+        // Only report the batches that reached the app.
+        if delivered {
+            self.change_pending = true;
+            if !self.change_callback_deferred {
+                self.flush_change_callback();
+            }
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl Drop for EventDispatcher {
+    fn drop(&mut self) {
+        for slot in self.next_event_slots.drain(..) {
+            let mut slot = lock_state(&slot);
+            slot.closed = true;
+            if let Some(waker) = slot.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dpi::LogicalSize;
+
+    use super::*;
+    use crate::tests::display;
+
+    /// A dispatcher with a reconnect window, and the events it delivers.
+    fn dispatcher(window: Duration) -> (EventDispatcher, Arc<Mutex<Vec<Event>>>) {
+        let delivered = Arc::new(Mutex::new(Vec::new()));
+        let mut dispatcher = EventDispatcher::default();
+        dispatcher.set_reconnect_window(Some(window));
+        dispatcher.set_callback(Box::new({
+            let delivered = Arc::clone(&delivered);
+            move |event| lock_state(&delivered).push(event)
+        }));
+
+        (dispatcher, delivered)
+    }
+
+    fn removal_of_the_last_display() -> EventBatch {
+        EventBatch::from_iter([
+            Event::Removed {
+                id: display(1, (0, 0), (1920, 1080), 1.0).id,
+                reason: ChangeReason::Hotplug,
+            },
+            Event::AllDisplaysRemoved,
+        ])
+    }
+
+    /// Count the calls of the function set with `on_any_change`.
+    fn count_changes(dispatcher: &mut EventDispatcher) -> Arc<AtomicU64> {
+        let changes = Arc::new(AtomicU64::new(0));
+        dispatcher.set_change_callback(Box::new({
+            let changes = Arc::clone(&changes);
+            move || {
+                changes.fetch_add(1, Ordering::Relaxed);
+            }
+        }));
+
+        changes
+    }
+
+    #[test]
+    fn reports_a_change_once_the_removal_is_delivered() {
+        let (mut dispatcher, _) = dispatcher(Duration::ZERO);
+        let changes = count_changes(&mut dispatcher);
+
+        dispatcher.dispatch(removal_of_the_last_display());
+        assert_eq!(changes.load(Ordering::Relaxed), 0);
+
+        dispatcher.flush_removals();
+        assert_eq!(changes.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn reports_a_change_once_resumed() {
+        let (mut dispatcher, _) = dispatcher(Duration::ZERO);
+        dispatcher.set_reconnect_window(None);
+        let changes = count_changes(&mut dispatcher);
+
+        dispatcher.suspend();
+        for width in [1280, 1920] {
+            dispatcher.dispatch(EventBatch::from_iter([Event::SizeChanged {
+                display: display(1, (0, 0), (width, 1080), 1.0),
+                before: LogicalSize::new(1024, 768),
+                after: LogicalSize::new(width, 1080),
+            }]));
+        }
+        assert_eq!(changes.load(Ordering::Relaxed), 0);
+
+        dispatcher.resume();
+        assert_eq!(changes.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn reports_the_batches_delivered_while_deferred_once() {
+        let (mut dispatcher, _) = dispatcher(Duration::ZERO);
+        dispatcher.set_reconnect_window(None);
+        let changes = count_changes(&mut dispatcher);
+
+        assert!(dispatcher.defer_change_callback());
+        for index in [1, 2] {
+            dispatcher.dispatch(EventBatch::from_iter([Event::Mirrored(display(
+                index,
+                (0, 0),
+                (1920, 1080),
+                1.0,
+            ))]));
+            assert!(!dispatcher.defer_change_callback());
+        }
+        assert_eq!(changes.load(Ordering::Relaxed), 0);
+
+        dispatcher.flush_change_callback();
+        assert_eq!(changes.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn keeps_the_events_delivered_between_polls() {
+        let mut dispatcher = EventDispatcher::default();
+        let added = || {
+            EventBatch::from_iter([Event::Added {
+                display: display(1, (0, 0), (1920, 1080), 1.0),
+                reason: ChangeReason::Hotplug,
+            }])
+        };
+
+        dispatcher.dispatch(added());
+        dispatcher.start_polling();
+        assert!(dispatcher.take_polled_events().is_empty());
+
+        // Delivered while the event loop of the app handles the messages.
+        dispatcher.dispatch(added());
+        dispatcher.start_polling();
+        assert_eq!(dispatcher.take_polled_events().len(), 1);
+        assert!(dispatcher.take_polled_events().is_empty());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn forgets_a_dropped_next_event() {
+        let mut dispatcher = EventDispatcher::default();
+        let next_event = dispatcher.next_event();
+        assert_eq!(dispatcher.next_event_slots.len(), 1);
+
+        drop(next_event);
+        dispatcher.dispatch(EventBatch::from_iter([Event::DisplaysRestored]));
+        assert!(dispatcher.next_event_slots.is_empty());
+    }
+
+    #[test]
+    fn holds_back_all_displays_removed_with_the_removal() {
+        let (mut dispatcher, delivered) = dispatcher(Duration::ZERO);
+
+        dispatcher.dispatch(removal_of_the_last_display());
+        assert!(lock_state(&delivered).is_empty());
+
+        dispatcher.flush_removals();
+        assert!(matches!(
+            &lock_state(&delivered)[..],
+            [Event::Removed { .. }, Event::AllDisplaysRemoved]
+        ));
+    }
+
+    #[test]
+    fn delivers_all_displays_removed_when_the_window_is_disabled() {
+        let (mut dispatcher, delivered) = dispatcher(Duration::from_secs(60));
+
+        dispatcher.dispatch(removal_of_the_last_display());
+        dispatcher.set_reconnect_window(None);
+
+        assert!(matches!(
+            &lock_state(&delivered)[..],
+            [Event::Removed { .. }, Event::AllDisplaysRemoved]
+        ));
+    }
+
+    #[test]
+    fn drops_the_headless_transition_of_a_reconnection() {
+        let (mut dispatcher, delivered) = dispatcher(Duration::from_secs(60));
+
+        dispatcher.dispatch(removal_of_the_last_display());
+        dispatcher.dispatch(EventBatch::from_iter([
+            Event::DisplaysRestored,
+            Event::Added {
+                display: display(1, (0, 0), (1920, 1080), 1.0),
+                reason: ChangeReason::Hotplug,
+            },
+        ]));
+        dispatcher.set_reconnect_window(None);
+
+        assert!(matches!(
+            &lock_state(&delivered)[..],
+            [Event::Reconnected { .. }]
+        ));
+    }
+}
@@ -0,0 +1,548 @@
+//! This module contains a minimal parser for [EDID][EDID], the data structure
+//! a monitor uses to describe its capabilities.
+//!
+//! Only the parts needed by this crate are parsed. The raw bytes are read by
+//! the platform-specific implementations.
+//!
+//! [EDID]: https://en.wikipedia.org/wiki/Extended_Display_Identification_Data
+
+use crate::{Chromaticity, SubpixelLayout, VrrInfo};
+
+const BLOCK_SIZE: usize = 128;
+const HEADER: [u8; 8] = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+
+/// The offsets of the four 18-byte descriptors of the base block.
+const DESCRIPTOR_OFFSETS: [usize; 4] = [54, 72, 90, 108];
+/// The tag of the display descriptor holding the serial number as text.
+const SERIAL_STRING_DESCRIPTOR_TAG: u8 = 0xFF;
+/// The tag of the display descriptor holding the product name.
+const PRODUCT_NAME_DESCRIPTOR_TAG: u8 = 0xFC;
+
+/// The tag of the CTA-861 extension block.
+const CTA_EXTENSION_TAG: u8 = 0x02;
+/// The tag of the DisplayID extension block.
+const DISPLAY_ID_EXTENSION_TAG: u8 = 0x70;
+
+/// The tag code of the vendor-specific data block in a CTA-861 extension.
+const CTA_VENDOR_SPECIFIC_TAG: u8 = 0x03;
+/// The IEEE OUI of AMD, whose vendor-specific data block carries the FreeSync range.
+const AMD_OUI: [u8; 3] = [0x1A, 0x00, 0x00];
+
+/// The tag of the Display Device Data block in DisplayID 1.3.
+const DISPLAY_ID_DISPLAY_DEVICE_TAG: u8 = 0x0C;
+/// The tag of the Adaptive-Sync data block in DisplayID 2.0.
+const DISPLAY_ID_ADAPTIVE_SYNC_TAG: u8 = 0x2B;
+/// The tags of the Tiled Display Topology data block in DisplayID 1.3 and 2.0.
+const DISPLAY_ID_TILED_DISPLAY_TAGS: [u8; 2] = [0x12, 0x28];
+
+/// Parsed EDID of a display.
+#[derive(Debug, Clone)]
+pub(crate) struct Edid {
+    bytes: Vec<u8>,
+}
+
+impl Edid {
+    /// Wrap the raw EDID bytes.
+    ///
+    /// Returns `None` if the bytes don't start with a valid EDID base block.
+    pub(crate) fn new(bytes: Vec<u8>) -> Option<Self> {
+        if bytes.len() < BLOCK_SIZE || bytes[..HEADER.len()] != HEADER {
+            return None;
+        }
+
+        Some(Self { bytes })
+    }
+
+    /// The manufacture date as the year and, if specified, the week.
+    ///
+    /// Returns `None` if the EDID holds the model year instead.
+    pub(crate) fn manufacture_date(&self) -> Option<(u16, Option<u8>)> {
+        // Byte 16 is the week (0 if unspecified, 0xFF if byte 17 is the model
+        // year) and byte 17 the year counted from 1990.
+        let (week, year) = (self.bytes[16], self.bytes[17]);
+        if week == 0xFF {
+            return None;
+        }
+
+        let week = (1..=54).contains(&week).then_some(week);
+        Some((1990 + year as u16, week))
+    }
+
+    /// The serial number in the header, or `None` if it's unset.
+    pub(crate) fn serial_number(&self) -> Option<u32> {
+        let serial = u32::from_le_bytes(self.bytes[12..16].try_into().unwrap());
+        (serial != 0).then_some(serial)
+    }
+
+    /// The serial number from the display descriptor holding it as text.
+    pub(crate) fn serial_string(&self) -> Option<String> {
+        self.descriptor_text(SERIAL_STRING_DESCRIPTOR_TAG)
+    }
+
+    /// The product name from the display descriptor holding it, e.g. `DELL U2720Q`.
+    pub(crate) fn product_name(&self) -> Option<String> {
+        self.descriptor_text(PRODUCT_NAME_DESCRIPTOR_TAG)
+    }
+
+    /// The text of the first display descriptor with the given tag.
+    fn descriptor_text(&self, tag: u8) -> Option<String> {
+        DESCRIPTOR_OFFSETS.iter().find_map(|&offset| {
+            let descriptor = &self.bytes[offset..offset + 18];
+            // Display descriptors start with a zero pixel clock, followed by the tag
+            // at byte 3 and up to 13 characters terminated by a line feed.
+            if descriptor[..2] != [0, 0] || descriptor[3] != tag {
+                return None;
+            }
+
+            let text = &descriptor[5..];
+            let len = text.iter().position(|&c| c == b'\n').unwrap_or(text.len());
+            let text = String::from_utf8_lossy(&text[..len]).trim().to_owned();
+            (!text.is_empty()).then_some(text)
+        })
+    }
+
+    /// The subpixel layout from the DisplayID Display Device Data block.
+    pub(crate) fn subpixel_layout(&self) -> SubpixelLayout {
+        let layout = self.display_id_data_blocks().find_map(|(tag, payload)| {
+            (tag == DISPLAY_ID_DISPLAY_DEVICE_TAG)
+                .then(|| payload.get(10).copied())
+                .flatten()
+        });
+
+        match layout {
+            Some(0x01) => SubpixelLayout::Rgb,
+            Some(0x02) => SubpixelLayout::VerticalRgb,
+            Some(0x03) => SubpixelLayout::Bgr,
+            Some(0x04) => SubpixelLayout::VerticalBgr,
+            // Quad, delta and mosaic arrangements have no stripes to render for.
+            Some(0x05..=0x0B) => SubpixelLayout::None,
+            _ => SubpixelLayout::Unknown,
+        }
+    }
+
+    /// The number of tiles of a tiled display as `(columns, rows)`, from the
+    /// DisplayID Tiled Display Topology data block.
+    pub(crate) fn tile_grid(&self) -> Option<(u32, u32)> {
+        self.display_id_data_blocks().find_map(|(tag, payload)| {
+            // Payload: capabilities, then the number of tiles in the low bits of
+            // byte 1 and the high bits of byte 3, both stored minus one.
+            let topology = payload.get(1..4)?;
+            DISPLAY_ID_TILED_DISPLAY_TAGS.contains(&tag).then(|| {
+                let columns = (topology[0] >> 4) as u32 | ((topology[2] >> 2) as u32 & 0x30);
+                let rows = (topology[0] & 0x0F) as u32 | (topology[2] as u32 & 0x30);
+                (columns + 1, rows + 1)
+            })
+        })
+    }
+
+    /// The physical size of the image area as `(width, height)` in millimeters.
+    pub(crate) fn physical_size_mm(&self) -> Option<(f64, f64)> {
+        // The first detailed timing descriptor holds the size in millimeters,
+        // with the upper 4 bits of both dimensions packed into byte 68.
+        let (width, height, upper) = (self.bytes[66], self.bytes[67], self.bytes[68]);
+        let width = width as u16 | (upper as u16 & 0xF0) << 4;
+        let height = height as u16 | (upper as u16 & 0x0F) << 8;
+        if width > 0 && height > 0 {
+            return Some((width as f64, height as f64));
+        }
+
+        // Otherwise fall back to the coarser size in centimeters of the base block.
+        let (width, height) = (self.bytes[21], self.bytes[22]);
+        (width > 0 && height > 0).then_some((width as f64 * 10.0, height as f64 * 10.0))
+    }
+
+    /// The chromaticity coordinates of the primaries and the white point, with the gamma.
+    pub(crate) fn chromaticity(&self) -> Chromaticity {
+        // Bytes 25 and 26 hold the lowest 2 bits of each 10-bit coordinate, and
+        // bytes 27 to 34 the highest 8 bits, in the order of the coordinates.
+        let low_bits = u16::from_be_bytes([self.bytes[25], self.bytes[26]]);
+        let coordinate = |index: usize| {
+            let high = self.bytes[27 + index] as u16;
+            let low = (low_bits >> (14 - 2 * index)) & 0x03;
+            (high << 2 | low) as f64 / 1024.0
+        };
+        let point = |index: usize| (coordinate(index * 2), coordinate(index * 2 + 1));
+
+        // Byte 23 is the gamma times 100 minus 100, or 0xFF if it's in an extension.
+        let gamma = (self.bytes[23] != 0xFF).then(|| (self.bytes[23] as f64 + 100.0) / 100.0);
+
+        Chromaticity {
+            red: point(0),
+            green: point(1),
+            blue: point(2),
+            white: point(3),
+            gamma,
+        }
+    }
+
+    /// The extension blocks following the base block.
+    fn extensions(&self) -> impl Iterator<Item = &[u8]> {
+        let count = self.bytes[126] as usize;
+        self.bytes[BLOCK_SIZE..]
+            .chunks_exact(BLOCK_SIZE)
+            .take(count)
+    }
+
+    /// The data blocks of all CTA-861 extensions as `(tag code, payload)`.
+    fn cta_data_blocks(&self) -> impl Iterator<Item = (u8, &[u8])> {
+        self.extensions()
+            .filter(|block| block[0] == CTA_EXTENSION_TAG)
+            .flat_map(|block| {
+                // Data blocks live between byte 4 and the start of the detailed timings.
+                let end = (block[2] as usize).clamp(4, BLOCK_SIZE - 1);
+                DataBlocks {
+                    bytes: &block[4..end],
+                }
+            })
+    }
+
+    /// The data blocks of all DisplayID extensions as `(tag, payload)`.
+    fn display_id_data_blocks(&self) -> impl Iterator<Item = (u8, &[u8])> {
+        self.extensions()
+            .filter(|block| block[0] == DISPLAY_ID_EXTENSION_TAG)
+            .flat_map(|block| {
+                // The section header (version, length, product type and extension
+                // count) follows the tag and is followed by the data blocks.
+                let end = (5 + block[2] as usize).min(BLOCK_SIZE - 1);
+                DisplayIdDataBlocks {
+                    bytes: &block[5..end],
+                }
+            })
+    }
+
+    /// The variable refresh rate range advertised by the display, in Hz.
+    ///
+    /// This is taken from the AMD vendor-specific data block (FreeSync) or the
+    /// DisplayID 2.0 Adaptive-Sync data block.
+    pub(crate) fn vrr_range(&self) -> Option<(f64, f64)> {
+        let amd = self.cta_data_blocks().find_map(|(tag, payload)| {
+            // Payload: OUI (3), version (2), minimum refresh rate, maximum refresh rate.
+            (tag == CTA_VENDOR_SPECIFIC_TAG && payload.len() >= 7 && payload[..3] == AMD_OUI)
+                .then(|| (payload[5] as f64, payload[6] as f64))
+        });
+
+        amd.or_else(|| {
+            self.display_id_data_blocks().find_map(|(tag, payload)| {
+                // Payload: 6-byte descriptors, whose byte 2 holds the minimum refresh
+                // rate and bytes 3 and 4 the maximum minus one in 10 bits.
+                let descriptor = payload.get(..6)?;
+                (tag == DISPLAY_ID_ADAPTIVE_SYNC_TAG).then(|| {
+                    let min = descriptor[2] as f64;
+                    let max =
+                        1.0 + (descriptor[3] as u16 | (descriptor[4] as u16 & 0x03) << 8) as f64;
+                    (min, max)
+                })
+            })
+        })
+        .filter(|(min, max)| *min > 0.0 && min < max)
+    }
+
+    /// The variable refresh rate capability advertised by the display.
+    pub(crate) fn vrr_info(&self) -> VrrInfo {
+        let range = self.vrr_range();
+
+        VrrInfo {
+            supported: range.is_some(),
+            min_hz: range.map(|(min, _)| min),
+            max_hz: range.map(|(_, max)| max),
+        }
+    }
+}
+
+/// An iterator over the data blocks of a CTA-861 extension.
+struct DataBlocks<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Iterator for DataBlocks<'a> {
+    type Item = (u8, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&header, rest) = self.bytes.split_first()?;
+        let len = (header & 0x1F) as usize;
+        let payload = rest.get(..len)?;
+
+        self.bytes = &rest[len..];
+        Some((header >> 5, payload))
+    }
+}
+
+/// An iterator over the data blocks of a DisplayID section.
+struct DisplayIdDataBlocks<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Iterator for DisplayIdDataBlocks<'a> {
+    type Item = (u8, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Each data block has a 3-byte header: tag, revision and payload length.
+        let header = self.bytes.get(..3)?;
+        let len = header[2] as usize;
+        let payload = self.bytes.get(3..3 + len)?;
+
+        self.bytes = &self.bytes[3 + len..];
+        Some((header[0], payload))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An EDID base block with the given number of extensions and no descriptors.
+    fn base_block(extension_count: u8) -> Vec<u8> {
+        let mut bytes = vec![0; BLOCK_SIZE];
+        bytes[..HEADER.len()].copy_from_slice(&HEADER);
+        bytes[126] = extension_count;
+        bytes
+    }
+
+    /// Write a display descriptor with the given tag and text at the given offset.
+    fn write_descriptor(bytes: &mut [u8], offset: usize, tag: u8, text: &str) {
+        let descriptor = &mut bytes[offset..offset + 18];
+        descriptor.fill(0);
+        descriptor[3] = tag;
+        // The text is terminated by a line feed and padded with spaces.
+        descriptor[5..].fill(b' ');
+        descriptor[5..5 + text.len()].copy_from_slice(text.as_bytes());
+        if text.len() < 13 {
+            descriptor[5 + text.len()] = b'\n';
+        }
+    }
+
+    /// An EDID with a DisplayID extension holding the given data block.
+    fn with_display_id_block(tag: u8, payload: &[u8]) -> Edid {
+        let mut extension = vec![0; BLOCK_SIZE];
+        extension[0] = DISPLAY_ID_EXTENSION_TAG;
+        extension[1] = 0x20;
+        extension[2] = (3 + payload.len()) as u8;
+        extension[5] = tag;
+        extension[7] = payload.len() as u8;
+        extension[8..8 + payload.len()].copy_from_slice(payload);
+
+        let mut bytes = base_block(1);
+        bytes.extend(extension);
+        Edid::new(bytes).unwrap()
+    }
+
+    #[test]
+    fn rejects_bytes_without_a_base_block() {
+        assert!(Edid::new(Vec::new()).is_none());
+        assert!(Edid::new(base_block(0)[..BLOCK_SIZE - 1].to_vec()).is_none());
+
+        let mut bytes = base_block(0);
+        bytes[0] = 0xFF;
+        assert!(Edid::new(bytes).is_none());
+    }
+
+    #[test]
+    fn reads_the_manufacture_date() {
+        let mut bytes = base_block(0);
+        bytes[16] = 12;
+        bytes[17] = 30;
+        assert_eq!(
+            Edid::new(bytes.clone()).unwrap().manufacture_date(),
+            Some((2020, Some(12)))
+        );
+
+        // The week is unspecified.
+        bytes[16] = 0;
+        assert_eq!(
+            Edid::new(bytes.clone()).unwrap().manufacture_date(),
+            Some((2020, None))
+        );
+
+        // Byte 17 is the model year instead.
+        bytes[16] = 0xFF;
+        assert_eq!(Edid::new(bytes).unwrap().manufacture_date(), None);
+    }
+
+    #[test]
+    fn reads_the_serial_number_of_the_header() {
+        let mut bytes = base_block(0);
+        assert_eq!(Edid::new(bytes.clone()).unwrap().serial_number(), None);
+
+        bytes[12..16].copy_from_slice(&[0x4C, 0x34, 0x32, 0x31]);
+        assert_eq!(Edid::new(bytes).unwrap().serial_number(), Some(0x3132_344C));
+    }
+
+    #[test]
+    fn reads_the_text_of_the_display_descriptors() {
+        let mut bytes = base_block(0);
+        // A detailed timing descriptor, with a non-zero pixel clock, comes first.
+        bytes[54..56].copy_from_slice(&[0x56, 0x5E]);
+        bytes[57] = SERIAL_STRING_DESCRIPTOR_TAG;
+        write_descriptor(&mut bytes, 72, SERIAL_STRING_DESCRIPTOR_TAG, "7MT0182R0TCL");
+        write_descriptor(&mut bytes, 90, PRODUCT_NAME_DESCRIPTOR_TAG, "DELL U2720Q");
+
+        let edid = Edid::new(bytes).unwrap();
+        assert_eq!(edid.serial_string().as_deref(), Some("7MT0182R0TCL"));
+        assert_eq!(edid.product_name().as_deref(), Some("DELL U2720Q"));
+    }
+
+    #[test]
+    fn reads_a_descriptor_text_without_a_line_feed() {
+        let mut bytes = base_block(0);
+        write_descriptor(
+            &mut bytes,
+            108,
+            PRODUCT_NAME_DESCRIPTOR_TAG,
+            "LG ULTRAFINE1",
+        );
+
+        let edid = Edid::new(bytes).unwrap();
+        assert_eq!(edid.product_name().as_deref(), Some("LG ULTRAFINE1"));
+        assert_eq!(edid.serial_string(), None);
+    }
+
+    #[test]
+    fn ignores_a_blank_descriptor_text() {
+        let mut bytes = base_block(0);
+        write_descriptor(&mut bytes, 72, SERIAL_STRING_DESCRIPTOR_TAG, "");
+
+        assert_eq!(Edid::new(bytes).unwrap().serial_string(), None);
+    }
+
+    #[test]
+    fn reads_the_physical_size() {
+        let mut bytes = base_block(0);
+        assert_eq!(Edid::new(bytes.clone()).unwrap().physical_size_mm(), None);
+
+        // 60 x 34 cm in the base block.
+        bytes[21..23].copy_from_slice(&[60, 34]);
+        assert_eq!(
+            Edid::new(bytes.clone()).unwrap().physical_size_mm(),
+            Some((600.0, 340.0))
+        );
+
+        // 597 x 336 mm (0x255 x 0x150) in the first detailed timing descriptor.
+        bytes[66..69].copy_from_slice(&[0x55, 0x50, 0x21]);
+        assert_eq!(
+            Edid::new(bytes).unwrap().physical_size_mm(),
+            Some((597.0, 336.0))
+        );
+    }
+
+    #[test]
+    fn reads_the_chromaticity() {
+        let mut bytes = base_block(0);
+        // The sRGB primaries and D65 white point as most monitors encode them,
+        // with a gamma of 2.2.
+        bytes[23] = 120;
+        bytes[25..35]
+            .copy_from_slice(&[0xEE, 0x91, 0xA3, 0x54, 0x4C, 0x99, 0x26, 0x0F, 0x50, 0x54]);
+
+        let chromaticity = Edid::new(bytes.clone()).unwrap().chromaticity();
+        assert_eq!(chromaticity.red, (0.6396484375, 0.330078125));
+        assert_eq!(chromaticity.green, (0.2998046875, 0.599609375));
+        assert_eq!(chromaticity.blue, (0.150390625, 0.0595703125));
+        assert_eq!(chromaticity.white, (0.3125, 0.3291015625));
+        assert_eq!(chromaticity.gamma, Some(2.2));
+
+        // The gamma is defined in an extension.
+        bytes[23] = 0xFF;
+        assert_eq!(Edid::new(bytes).unwrap().chromaticity().gamma, None);
+    }
+
+    #[test]
+    fn reads_the_freesync_range() {
+        let mut extension = vec![0; BLOCK_SIZE];
+        extension[0] = CTA_EXTENSION_TAG;
+        extension[1] = 0x03;
+        // A vendor-specific data block of 8 bytes: the AMD OUI, the version,
+        // 48 Hz and 144 Hz, and a trailing flags byte.
+        let data_block = [0x68, 0x1A, 0x00, 0x00, 0x01, 0x01, 48, 144, 0x00];
+        extension[4..4 + data_block.len()].copy_from_slice(&data_block);
+        extension[2] = (4 + data_block.len()) as u8;
+
+        let mut bytes = base_block(1);
+        bytes.extend(extension);
+        let edid = Edid::new(bytes).unwrap();
+        assert_eq!(edid.vrr_range(), Some((48.0, 144.0)));
+
+        let vrr = edid.vrr_info();
+        assert!(vrr.supported);
+        assert_eq!((vrr.min_hz, vrr.max_hz), (Some(48.0), Some(144.0)));
+    }
+
+    #[test]
+    fn ignores_the_extensions_past_the_extension_count() {
+        let mut bytes = with_display_id_block(
+            DISPLAY_ID_ADAPTIVE_SYNC_TAG,
+            &[0x00, 0x00, 48, 0xA4, 0x00, 0x00],
+        )
+        .bytes;
+        bytes[126] = 0;
+
+        assert_eq!(Edid::new(bytes).unwrap().vrr_range(), None);
+    }
+
+    #[test]
+    fn reads_the_tile_grid() {
+        // A 2x1 tiled display such as the LG UltraFine 5K: capabilities, then the
+        // columns and rows minus one, with no high bits.
+        for tag in DISPLAY_ID_TILED_DISPLAY_TAGS {
+            let edid = with_display_id_block(tag, &[0x82, 0x10, 0x00, 0x00]);
+            assert_eq!(edid.tile_grid(), Some((2, 1)));
+        }
+
+        // 20 x 18 tiles need the high bits of byte 3.
+        let edid =
+            with_display_id_block(DISPLAY_ID_TILED_DISPLAY_TAGS[1], &[0x00, 0x31, 0x00, 0x50]);
+        assert_eq!(edid.tile_grid(), Some((20, 18)));
+
+        let edid = Edid::new(base_block(0)).unwrap();
+        assert_eq!(edid.tile_grid(), None);
+    }
+
+    #[test]
+    fn reads_the_subpixel_layout() {
+        let cases = [
+            (0x01, SubpixelLayout::Rgb),
+            (0x02, SubpixelLayout::VerticalRgb),
+            (0x03, SubpixelLayout::Bgr),
+            (0x04, SubpixelLayout::VerticalBgr),
+            (0x05, SubpixelLayout::None),
+            (0x00, SubpixelLayout::Unknown),
+        ];
+
+        for (layout, expected) in cases {
+            let mut payload = [0; 13];
+            payload[10] = layout;
+            let edid = with_display_id_block(DISPLAY_ID_DISPLAY_DEVICE_TAG, &payload);
+            assert_eq!(edid.subpixel_layout(), expected);
+        }
+
+        let edid = Edid::new(base_block(0)).unwrap();
+        assert_eq!(edid.subpixel_layout(), SubpixelLayout::Unknown);
+    }
+
+    #[test]
+    fn reads_the_display_id_adaptive_sync_range() {
+        // A 48-165 Hz descriptor: flags, range duration, minimum, maximum - 1
+        // (164 = 0xA4) in 10 bits, and the supported duration flags.
+        let edid = with_display_id_block(
+            DISPLAY_ID_ADAPTIVE_SYNC_TAG,
+            &[0x00, 0x00, 48, 0xA4, 0x00, 0x00],
+        );
+        assert_eq!(edid.vrr_range(), Some((48.0, 165.0)));
+    }
+
+    #[test]
+    fn reads_a_display_id_adaptive_sync_maximum_above_255() {
+        // 40-360 Hz, whose maximum - 1 (359 = 0x167) needs the high bits.
+        let edid = with_display_id_block(
+            DISPLAY_ID_ADAPTIVE_SYNC_TAG,
+            &[0x00, 0x00, 40, 0x67, 0x01, 0x00],
+        );
+        assert_eq!(edid.vrr_range(), Some((40.0, 360.0)));
+    }
+
+    #[test]
+    fn has_no_vrr_range_without_a_data_block() {
+        let edid = Edid::new(base_block(0)).unwrap();
+        assert_eq!(edid.vrr_range(), None);
+        assert!(!edid.vrr_info().supported);
+    }
+}
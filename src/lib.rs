@@ -38,22 +38,41 @@
 //!   DPI awareness itself. Calling this function might conflict with the
 //!   framework's settings. In such cases, defer to the framework's DPI management.
 
+use std::{
+    cmp::Ordering,
+    sync::{Mutex, PoisonError, mpsc},
+    time::{Duration, Instant},
+};
+
 use dpi::{LogicalPosition, LogicalSize};
+use smallvec::SmallVec;
 
+use dispatch::EventKind;
+use edid::Edid;
+
+mod dispatch;
+mod edid;
 #[cfg(target_os = "macos")]
 pub mod macos;
+mod tracking;
+#[cfg(feature = "serde")]
+pub mod transport;
 #[cfg(target_os = "windows")]
 pub mod windows;
 
 #[cfg(target_os = "macos")]
 use macos::{
     MacOSDisplayId as PlatformDisplayId, MacOSDisplayObserver as PlatformDisplayObserver,
-    MacOSError as PlatformError, get_macos_displays as get_platform_displays,
+    MacOSError as PlatformError, MacOSStopToken as PlatformStopToken,
+    STANDARD_SCALE_FACTORS as PLATFORM_STANDARD_SCALE_FACTORS,
+    get_macos_displays as get_platform_displays, report_event as report_platform_event,
 };
 #[cfg(target_os = "windows")]
 use windows::{
+    STANDARD_SCALE_FACTORS as PLATFORM_STANDARD_SCALE_FACTORS,
     WindowsDisplayId as PlatformDisplayId, WindowsDisplayObserver as PlatformDisplayObserver,
-    WindowsError as PlatformError, get_windows_displays as get_platform_displays,
+    WindowsError as PlatformError, WindowsStopToken as PlatformStopToken,
+    get_windows_displays as get_platform_displays, report_event as report_platform_event,
 };
 
 /// The error type for this crate.
@@ -65,6 +84,12 @@ pub enum Error {
     /// An error occurred in the platform-specific implementation.
     #[error("A platform-specific error has occurred.")]
     PlatformError(PlatformError),
+    /// The operation did not complete within the given time.
+    #[error("The operation timed out.")]
+    Timeout,
+    /// There is no display, e.g. in a headless session.
+    #[error("No display was found.")]
+    NoDisplays,
 }
 
 impl From<PlatformError> for Error {
@@ -74,8 +99,376 @@ impl From<PlatformError> for Error {
 }
 
 /// Get all available displays.
+///
+/// The geometry is in [`CoordinateSpace::Logical`]. Use [`get_displays_in`] for
+/// another coordinate space.
 pub fn get_displays() -> Result<Vec<Display>, Error> {
-    Ok(get_platform_displays()?)
+    get_displays_in(CoordinateSpace::default())
+}
+
+/// Get all available displays with the geometry in the given coordinate space.
+pub fn get_displays_in(space: CoordinateSpace) -> Result<Vec<Display>, Error> {
+    let mut displays = get_platform_displays()?;
+    space.convert(&mut displays);
+    Ok(displays)
+}
+
+/// The unit of the origin and size of a [`Display`].
+///
+/// # Convention
+/// The size of each display is converted by its own scale factor, while all
+/// origins are converted by the scale factor of the primary display, as in
+/// [`logical_layout`]. This keeps the relative arrangement of the displays,
+/// though the edges of displays with a scale factor different from the primary
+/// one may not touch exactly after the conversion.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CoordinateSpace {
+    /// Logical pixels, independent of the scale factor: points on macOS and
+    /// device-independent pixels on Windows.
+    #[default]
+    Logical,
+    /// Physical pixels of the framebuffer.
+    Physical,
+}
+
+impl CoordinateSpace {
+    /// The coordinate space the platform reports the displays in.
+    const NATIVE: Self = if cfg!(target_os = "windows") {
+        Self::Physical
+    } else {
+        Self::Logical
+    };
+
+    /// Convert the displays reported by the platform into this coordinate space.
+    pub(crate) fn convert(self, displays: &mut [Display]) {
+        let primary_scale_factor = displays
+            .iter()
+            .find(|display| display.is_primary)
+            .map_or(1.0, |display| display.scale_factor);
+
+        for display in displays {
+            self.convert_display(display, primary_scale_factor);
+        }
+    }
+
+    /// Convert a display reported by the platform into this coordinate space,
+    /// given the scale factor of the primary display.
+    pub(crate) fn convert_display(self, display: &mut Display, primary_scale_factor: f64) {
+        let (size_factor, origin_factor) = match (Self::NATIVE, self) {
+            (Self::Physical, Self::Logical) => {
+                (1.0 / display.scale_factor, 1.0 / primary_scale_factor)
+            }
+            (Self::Logical, Self::Physical) => (display.scale_factor, primary_scale_factor),
+            _ => return,
+        };
+
+        display.origin = LogicalPosition::new(
+            (display.origin.x as f64 * origin_factor).round() as i32,
+            (display.origin.y as f64 * origin_factor).round() as i32,
+        );
+        display.size = LogicalSize::new(
+            (display.size.width as f64 * size_factor).round() as u32,
+            (display.size.height as f64 * size_factor).round() as u32,
+        );
+    }
+}
+
+/// The displays fetched by [`get_displays_cached`], with the time they were fetched at.
+static DISPLAYS_CACHE: Mutex<Option<(Instant, Vec<Display>)>> = Mutex::new(None);
+
+/// Get all available displays, reusing a recent snapshot if there is one.
+///
+/// If the displays were fetched by this function within `max_age`, the
+/// snapshot is returned instead of enumerating the displays again. This
+/// avoids redundant enumeration when several parts of an app ask for the
+/// displays at nearly the same time.
+///
+/// The snapshot is discarded whenever a [`DisplayObserver`] detects a change,
+/// so it's never served after a known reconfiguration.
+pub fn get_displays_cached(max_age: Duration) -> Result<Vec<Display>, Error> {
+    let mut cache = DISPLAYS_CACHE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    if let Some((fetched_at, displays)) = cache.as_ref()
+        && fetched_at.elapsed() <= max_age
+    {
+        return Ok(displays.clone());
+    }
+
+    let displays = get_displays()?;
+    *cache = Some((Instant::now(), displays.clone()));
+
+    Ok(displays)
+}
+
+/// Discard the snapshot used by [`get_displays_cached`].
+pub(crate) fn invalidate_displays_cache() {
+    *DISPLAYS_CACHE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// Get all available displays except the built-in ones.
+///
+/// This is useful when you want to offer only external monitors, e.g. as
+/// projection targets of a presentation app.
+pub fn get_external_displays() -> Result<Vec<Display>, Error> {
+    let mut displays = get_displays()?;
+    displays.retain(|display| !display.is_builtin);
+    Ok(displays)
+}
+
+/// Get all available displays, grouped by [`Display::same_resolution_as`].
+///
+/// The groups and the displays in them keep the order of [`get_displays`].
+pub fn get_displays_grouped_by_resolution() -> Result<Vec<Vec<Display>>, Error> {
+    let mut groups: Vec<Vec<Display>> = Vec::new();
+
+    for display in get_displays()? {
+        match groups
+            .iter_mut()
+            .find(|group| group[0].same_resolution_as(&display))
+        {
+            Some(group) => group.push(display),
+            None => groups.push(vec![display]),
+        }
+    }
+
+    Ok(groups)
+}
+
+/// Get all available displays, optionally leaving out the virtual ones.
+///
+/// Virtual displays are created by software such as screen-sharing tools
+/// rather than backed by a monitor. See [`Display::is_virtual`] for how they
+/// are detected.
+pub fn get_displays_filtered(include_virtual: bool) -> Result<Vec<Display>, Error> {
+    let mut displays = get_displays()?;
+    if !include_virtual {
+        displays.retain(|display| !display.is_virtual);
+    }
+    Ok(displays)
+}
+
+/// Get the display with the given id, or `None` if it's not connected.
+///
+/// The geometry is in [`CoordinateSpace::Logical`], as in [`get_displays`].
+pub fn get_display_by_id(id: &DisplayId) -> Result<Option<Display>, Error> {
+    Ok(get_displays()?
+        .into_iter()
+        .find(|display| display.id == *id))
+}
+
+/// Get the display currently connected with the given handle, if any.
+///
+/// The geometry is in [`CoordinateSpace::Logical`], as in [`get_displays`].
+pub fn get_display_by_handle(handle: &DisplayHandle) -> Result<Option<Display>, Error> {
+    Ok(get_displays()?
+        .into_iter()
+        .find(|display| display.handle() == *handle))
+}
+
+/// Get the number of physical pixels of all displays together.
+///
+/// Displays mirroring another display are left out, since they show the same
+/// pixels as their [`Display::mirror_source`]. The sizes are in
+/// [`CoordinateSpace::Physical`], i.e. scaled by the scale factor of each display.
+pub fn total_pixel_count() -> Result<u64, Error> {
+    Ok(get_displays_in(CoordinateSpace::Physical)?
+        .iter()
+        .filter(|display| display.mirror_source.is_none())
+        .map(|display| u64::from(display.size.width) * u64::from(display.size.height))
+        .sum())
+}
+
+/// Describe the whole display configuration as a JSON document.
+///
+/// The document contains every display with all its properties, the ID of
+/// the primary display and the bounds of the virtual screen spanning all
+/// displays. It's meant for logging and bug reports, not for parsing back.
+#[cfg(feature = "serde")]
+pub fn dump_configuration() -> Result<serde_json::Value, Error> {
+    let displays = get_displays()?;
+    let primary = displays
+        .iter()
+        .find(|display| display.is_primary)
+        .map(|display| &display.id);
+
+    let virtual_bounds = displays
+        .iter()
+        .map(|display| edges(display.origin, display.size))
+        .reduce(|(left, top, right, bottom), (l, t, r, b)| {
+            (left.min(l), top.min(t), right.max(r), bottom.max(b))
+        })
+        .map(|(left, top, right, bottom)| {
+            serde_json::json!({
+                "x": left,
+                "y": top,
+                "width": right - left,
+                "height": bottom - top,
+            })
+        });
+
+    Ok(serde_json::json!({
+        "platform": std::env::consts::OS,
+        "display_count": displays.len(),
+        "primary": primary,
+        "virtual_bounds": virtual_bounds,
+        "displays": displays,
+    }))
+}
+
+/// Get the bounds of every display in a common logical space, independent of
+/// the scale factor of each display.
+///
+/// # Convention
+/// The size of each display is divided by its own scale factor, while all
+/// origins are divided by the scale factor of the primary display. This keeps
+/// the primary display at `(0, 0)` with its logical size and the relative
+/// arrangement of the other displays, though the edges of displays with a
+/// scale factor different from the primary one may not touch exactly.
+///
+/// # Platform-specific
+/// - **Windows**: The conversion above is applied to the pixel coordinates.
+/// - **macOS**: The coordinates are already in points shared by all displays,
+///   so they're returned as they are.
+pub fn logical_layout() -> Result<Vec<(DisplayId, Rect)>, Error> {
+    // Convert from the native coordinates to avoid rounding twice.
+    let displays = get_platform_displays()?;
+
+    // Only Windows reports pixel coordinates that need to be converted.
+    let scale_factor_of = |display: &Display| {
+        if cfg!(target_os = "windows") {
+            display.scale_factor
+        } else {
+            1.0
+        }
+    };
+    let primary_scale_factor = displays
+        .iter()
+        .find(|display| display.is_primary)
+        .map_or(1.0, scale_factor_of);
+
+    Ok(displays
+        .into_iter()
+        .map(|display| {
+            let scale_factor = scale_factor_of(&display);
+            let rect = Rect {
+                origin: LogicalPosition::new(
+                    display.origin.x as f64 / primary_scale_factor,
+                    display.origin.y as f64 / primary_scale_factor,
+                ),
+                size: LogicalSize::new(
+                    display.size.width as f64 / scale_factor,
+                    display.size.height as f64 / scale_factor,
+                ),
+            };
+
+            (display.id, rect)
+        })
+        .collect())
+}
+
+/// Get the bounds of the primary display, e.g. to center a splash screen on it.
+///
+/// This is the rectangle of the primary display in [`logical_layout`], without
+/// enumerating every display.
+///
+/// # Errors
+/// Returns [`Error::PlatformError`] if the bounds can't be read, or
+/// [`Error::NoDisplays`] if there is no display on macOS.
+pub fn primary_bounds() -> Result<Rect, Error> {
+    #[cfg(target_os = "windows")]
+    {
+        Ok(windows::get_primary_bounds()?)
+    }
+    #[cfg(target_os = "macos")]
+    {
+        macos::get_primary_bounds().ok_or(Error::NoDisplays)
+    }
+}
+
+/// Move a point from one display to the same physical pixel of another display,
+/// e.g. when a window moves from a 2x display to a 1x display.
+///
+/// The point is in the same coordinates as [`Display::origin`], and is taken
+/// relative to the origin of `from`. That offset is converted to physical
+/// pixels with the scale factor of `from`, then back to logical pixels with
+/// the scale factor of `to`, and added to the origin of `to`. Both displays
+/// are expected in [`CoordinateSpace::Logical`].
+pub fn translate_point(
+    point: LogicalPosition<f64>,
+    from: &Display,
+    to: &Display,
+) -> LogicalPosition<f64> {
+    let scale = from.scale_factor / to.scale_factor;
+
+    LogicalPosition::new(
+        f64::from(to.origin.x) + (point.x - f64::from(from.origin.x)) * scale,
+        f64::from(to.origin.y) + (point.y - f64::from(from.origin.y)) * scale,
+    )
+}
+
+/// Get the display that the given rectangle overlaps the most.
+///
+/// If the rectangle doesn't overlap any display, the display closest to it is
+/// returned instead. This is useful to move a window back onto a screen, e.g.
+/// when restoring a saved window position after a monitor was disconnected.
+///
+/// Returns `None` only if there is no display at all.
+pub fn display_for_rect(
+    origin: LogicalPosition<i32>,
+    size: LogicalSize<u32>,
+) -> Result<Option<Display>, Error> {
+    let displays = get_displays()?;
+
+    let overlapping = displays
+        .iter()
+        .map(|display| (display, overlap_area(display, origin, size)))
+        .filter(|(_, area)| *area > 0)
+        .max_by_key(|(_, area)| *area)
+        .map(|(display, _)| display);
+
+    let display = overlapping.or_else(|| {
+        displays
+            .iter()
+            .min_by_key(|display| distance_squared(display, origin, size))
+    });
+
+    Ok(display.cloned())
+}
+
+/// The edges of a rectangle as `(left, top, right, bottom)`.
+fn edges(origin: LogicalPosition<i32>, size: LogicalSize<u32>) -> (i64, i64, i64, i64) {
+    let (x, y) = (origin.x as i64, origin.y as i64);
+    (x, y, x + size.width as i64, y + size.height as i64)
+}
+
+fn overlap_area(display: &Display, origin: LogicalPosition<i32>, size: LogicalSize<u32>) -> i64 {
+    let (left, top, right, bottom) = edges(display.origin, display.size);
+    let (rect_left, rect_top, rect_right, rect_bottom) = edges(origin, size);
+
+    let width = right.min(rect_right) - left.max(rect_left);
+    let height = bottom.min(rect_bottom) - top.max(rect_top);
+
+    width.max(0) * height.max(0)
+}
+
+/// The squared distance between the closest points of the display and the rectangle.
+fn distance_squared(
+    display: &Display,
+    origin: LogicalPosition<i32>,
+    size: LogicalSize<u32>,
+) -> i64 {
+    let (left, top, right, bottom) = edges(display.origin, display.size);
+    let (rect_left, rect_top, rect_right, rect_bottom) = edges(origin, size);
+
+    let dx = (left - rect_right).max(rect_left - right).max(0);
+    let dy = (top - rect_bottom).max(rect_top - bottom).max(0);
+
+    dx * dx + dy * dy
 }
 
 /// A unique identifier for a display.
@@ -87,7 +480,8 @@ pub fn get_displays() -> Result<Vec<Display>, Error> {
 ///
 /// [device path]: https://learn.microsoft.com/en-us/dotnet/standard/io/file-path-formats#dos-device-paths
 /// [CGDirectDisplayID]: https://developer.apple.com/documentation/coregraphics/cgdirectdisplayid?language=objc
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DisplayId(PlatformDisplayId);
 
 impl From<PlatformDisplayId> for DisplayId {
@@ -97,6 +491,28 @@ impl From<PlatformDisplayId> for DisplayId {
 }
 
 impl DisplayId {
+    /// Creates a display id from the GDI device name of a display, e.g. `\\.\DISPLAY1`
+    /// as found in the `szDevice` field of [`MONITORINFOEXW`][MONITORINFOEXW] or
+    /// the `DeviceName` field of `DISPLAY_DEVICEW`.
+    ///
+    /// This is not the device interface path of the monitor, e.g. one received
+    /// in a device notification, which never matches the id of a display.
+    ///
+    /// [MONITORINFOEXW]: https://learn.microsoft.com/en-us/windows/win32/api/winuser/ns-winuser-monitorinfoexw
+    #[cfg(target_os = "windows")]
+    pub fn from_windows_device_name(device_name: std::ffi::OsString) -> Self {
+        Self(PlatformDisplayId::new(device_name))
+    }
+
+    /// Creates a display id from a [`CGDirectDisplayID`][CGDirectDisplayID],
+    /// e.g. one obtained from another macOS API.
+    ///
+    /// [CGDirectDisplayID]: https://developer.apple.com/documentation/coregraphics/cgdirectdisplayid?language=objc
+    #[cfg(target_os = "macos")]
+    pub fn from_macos(id: PlatformDisplayId) -> Self {
+        Self(id)
+    }
+
     /// Returns the Windows-specific display id.
     #[cfg(target_os = "windows")]
     pub fn windows_id(&self) -> &PlatformDisplayId {
@@ -108,6 +524,68 @@ impl DisplayId {
     pub fn macos_id(&self) -> &PlatformDisplayId {
         &self.0
     }
+
+    /// Check whether this display and the other one are driven by the same graphics adapter.
+    ///
+    /// Returns `false` if the adapter of either display can't be determined.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: Compares the adapter [`LUID`][LUID] of the display paths.
+    /// - **macOS**: Compares the registry ID of the Metal device driving the displays.
+    ///
+    /// [LUID]: https://learn.microsoft.com/en-us/windows/win32/api/winnt/ns-winnt-luid
+    pub fn same_adapter(&self, other: &DisplayId) -> Result<bool, Error> {
+        #[cfg(target_os = "windows")]
+        {
+            let (Some(a), Some(b)) = (self.0.adapter_id()?, other.0.adapter_id()?) else {
+                return Ok(false);
+            };
+
+            Ok(a == b)
+        }
+        #[cfg(target_os = "macos")]
+        {
+            let a = macos::get_gpu_registry_id(self.0);
+            let b = macos::get_gpu_registry_id(other.0);
+
+            Ok(a.is_some() && a == b)
+        }
+    }
+}
+
+/// A handle of a display that stays the same across reconnections and reboots,
+/// e.g. to key the settings of a display in a database.
+///
+/// Unlike [`DisplayId`], which identifies a display only while it's connected,
+/// this identifies the monitor itself. Get it with [`Display::handle`] and
+/// find the display again with [`get_display_by_handle`].
+///
+/// # Platform-specific
+/// - **Windows**: The device path of the monitor, like
+///   `\\?\DISPLAY#DEL40F8#5&2a2e2c2&0&UID4352#{...}`. If the monitor of the
+///   display can't be found, this falls back to the GDI device name, like
+///   `\\.\DISPLAY1`, which is *not* persistent: it may change on reboot or
+///   be reused by another monitor. Such a handle starts with `\\.\`.
+/// - **macOS**: The UUID macOS assigns to the display, from
+///   `CGDisplayCreateUUIDFromDisplayID`, which tells identical monitors apart.
+///   If there's no UUID, this falls back to the vendor, model and serial
+///   numbers of the monitor, which are the same for two identical monitors
+///   without a serial number.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DisplayHandle(String);
+
+impl DisplayHandle {
+    /// Returns the handle as a string, e.g. to store it.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for DisplayHandle {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
 }
 
 /// A display.
@@ -115,110 +593,2293 @@ impl DisplayId {
 /// This struct provides a cross-platform interface to interact with displays.
 /// You can get the display's id, origin, size, and check if it's mirrored.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Display {
     /// The unique identifier of the display.
     pub id: DisplayId,
     /// The origin of the display.
+    ///
+    /// This is in [`CoordinateSpace::Logical`] unless another coordinate space
+    /// was requested, e.g. with [`get_displays_in`].
     pub origin: LogicalPosition<i32>,
-    /// The size of the display.
+    /// The size of the display, in the same coordinate space as [`Display::origin`].
     pub size: LogicalSize<u32>,
     /// The scale factor of the display.
     pub scale_factor: f64,
     /// Whether the display is the primary monitor.
+    ///
+    /// This is the display the OS considers its main display, which holds the
+    /// menu bar or taskbar and whose origin is `(0, 0)`. Exactly one display
+    /// is primary at a time, even if it's part of a mirror set.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: The monitor flagged with [`MONITORINFOF_PRIMARY`][MONITORINFOF_PRIMARY].
+    /// - **macOS**: The display returned by [`CGMainDisplayID`][CGMainDisplayID].
+    ///
+    /// [MONITORINFOF_PRIMARY]: https://learn.microsoft.com/en-us/windows/win32/api/winuser/ns-winuser-monitorinfo
+    /// [CGMainDisplayID]: https://developer.apple.com/documentation/coregraphics/cgmaindisplayid()?language=objc
     pub is_primary: bool,
     /// Whether the display is mirrored.
     pub is_mirrored: bool,
+    /// The display this display mirrors, or `None` if it isn't mirroring another display.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: Always `None`, since the displays of a clone set show the
+    ///   same source and none of them is the original.
+    /// - **macOS**: The value of [`CGDisplayMirrorsDisplay`][CGDisplayMirrorsDisplay].
+    ///
+    /// [CGDisplayMirrorsDisplay]: https://developer.apple.com/documentation/coregraphics/cgdisplaymirrorsdisplay(_:)?language=objc
+    pub mirror_source: Option<DisplayId>,
+    /// Whether the display is built into the device, like a laptop panel.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: Determined by the output technology of the display path
+    ///   (internal, embedded DisplayPort or embedded UDI).
+    /// - **macOS**: The value of [`CGDisplayIsBuiltin`][CGDisplayIsBuiltin].
+    ///
+    /// [CGDisplayIsBuiltin]: https://developer.apple.com/documentation/coregraphics/cgdisplayisbuiltin(_:)?language=objc
+    pub is_builtin: bool,
+    /// Whether the display is virtual, i.e. created by software such as a
+    /// screen-sharing tool rather than backed by a monitor.
+    ///
+    /// This is a best-effort guess.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: The output technology of the display path is indirect
+    ///   virtual, or the monitor matches a well-known virtual display driver.
+    /// - **macOS**: The vendor Core Graphics reports for the display is unknown.
+    pub is_virtual: bool,
+    /// The rotation of the display as configured by the user.
+    pub rotation: DisplayRotation,
+    /// The refresh rate of the current mode of the display in Hz.
+    ///
+    /// This is `None` if the platform reports no fixed refresh rate.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: Whole numbers only, e.g. `59.0` for 59.94 Hz, as reported
+    ///   by the current display settings.
+    /// - **macOS**: `None` for most built-in panels, whose refresh rate isn't fixed.
+    pub refresh_rate: Option<f64>,
+    /// The variable refresh rate (FreeSync, G-Sync Compatible, Adaptive-Sync)
+    /// capability of the display.
+    ///
+    /// This is `None` if the capability can't be determined, e.g. because the
+    /// EDID of the display is unavailable.
+    pub vrr: Option<VrrInfo>,
+    /// The arrangement of the subpixels of the display, for subpixel text rendering.
+    ///
+    /// This is `None` if the EDID of the display is unavailable.
+    pub subpixel: Option<SubpixelLayout>,
+    /// The input devices integrated with the display, like a touch screen.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: The pointer devices that Windows maps to the monitor.
+    /// - **macOS**: Always without touch and pen, as macOS has no touch or pen
+    ///   displays and doesn't map graphics tablets to a display.
+    pub input_capabilities: InputCapabilities,
 }
 
-/// An event that occurs when the display configuration changes.
-#[derive(Debug, Clone)]
-pub enum Event {
-    /// A display was added.
-    Added(Display),
-    /// A display was removed.
-    Removed(DisplayId),
-    /// The size of a display changed.
-    SizeChanged {
-        display: Display,
-        before: LogicalSize<u32>,
-        after: LogicalSize<u32>,
-    },
-    /// The origin of a display changed.
-    OriginChanged {
-        display: Display,
-        before: LogicalPosition<i32>,
-        after: LogicalPosition<i32>,
-    },
-    /// A display was mirrored.
-    Mirrored(Display),
-    /// A display was unmirrored.
-    UnMirrored(Display),
-}
+impl Display {
+    /// Whether this is the main display of the OS, the same as [`Display::is_primary`].
+    ///
+    /// This is named after the macOS term, see [`Display::is_primary`] for how it's
+    /// determined on each platform.
+    pub fn is_main(&self) -> bool {
+        self.is_primary
+    }
 
-/// A callback function that is called when a display event occurs.
-pub type DisplayEventCallback = Box<dyn FnMut(Event) + Send + 'static>;
+    /// Get the capabilities of the display as a set of flags, for filtering the
+    /// displays by capability.
+    ///
+    /// The flags are derived from the fields of the display, except
+    /// [`Capabilities::HDR`], which is queried from the OS like
+    /// [`DisplayMode::hdr_capable`].
+    pub fn capabilities(&self) -> Capabilities {
+        #[cfg(target_os = "windows")]
+        let hdr = windows::is_hdr_capable(self.id.windows_id());
+        #[cfg(target_os = "macos")]
+        let hdr = macos::is_display_hdr_capable(*self.id.macos_id());
 
-/// A display observer that monitors changes to the display configuration.
-pub struct DisplayObserver {
-    inner: PlatformDisplayObserver,
-}
+        let mut capabilities = Capabilities::empty();
+        capabilities.set(Capabilities::HDR, hdr);
+        capabilities.set(Capabilities::VRR, self.vrr.is_some_and(|vrr| vrr.supported));
+        capabilities.set(Capabilities::BUILTIN, self.is_builtin);
+        capabilities.set(Capabilities::TOUCH, self.input_capabilities.touch);
+        capabilities.set(Capabilities::PEN, self.input_capabilities.pen);
+        capabilities.set(Capabilities::MIRRORED, self.is_mirrored);
+        capabilities.set(Capabilities::PRIMARY, self.is_primary);
+        capabilities
+    }
 
-impl From<PlatformDisplayObserver> for DisplayObserver {
-    fn from(inner: PlatformDisplayObserver) -> Self {
-        Self { inner }
+    /// Get the handle of the display that stays the same across reconnections
+    /// and reboots, see [`DisplayHandle`].
+    pub fn handle(&self) -> DisplayHandle {
+        #[cfg(target_os = "windows")]
+        let key = windows::get_persistent_key(self.id.windows_id());
+        #[cfg(target_os = "macos")]
+        let key = macos::get_persistent_key(*self.id.macos_id());
+
+        DisplayHandle(key)
     }
-}
 
-impl From<DisplayObserver> for PlatformDisplayObserver {
-    fn from(value: DisplayObserver) -> Self {
-        value.inner
+    /// Whether the origin and size are plausible for a display.
+    ///
+    /// In the middle of a reconfiguration, the system may report a half-applied
+    /// state with a zero size or an origin far out of range. The size must be
+    /// non-zero and the display must lie within the coordinate range of a
+    /// virtual desktop, which is ±32768 on both platforms.
+    pub fn is_sane(&self) -> bool {
+        const COORDINATE_LIMIT: i64 = 1 << 15;
+
+        let range = -COORDINATE_LIMIT..=COORDINATE_LIMIT;
+        let (x, y) = (self.origin.x as i64, self.origin.y as i64);
+        let (width, height) = (self.size.width as i64, self.size.height as i64);
+
+        width > 0
+            && height > 0
+            && range.contains(&x)
+            && range.contains(&y)
+            && range.contains(&(x + width))
+            && range.contains(&(y + height))
     }
-}
 
-impl DisplayObserver {
-    /// Create the display observer instance.
-    pub fn new() -> Result<Self, Error> {
-        Ok(Self {
-            inner: PlatformDisplayObserver::new()?,
-        })
+    /// Get the affine transform from the logical space of the display to its
+    /// physical framebuffer, e.g. to composite an overlay onto a rotated display.
+    ///
+    /// The transform is `[a, b, c, d, tx, ty]`, laid out like a `CGAffineTransform`,
+    /// which maps the point `(x, y)` relative to the origin of the display to
+    /// `(a * x + c * y + tx, b * x + d * y + ty)` in the framebuffer. The
+    /// framebuffer keeps the native orientation of the panel, so for
+    /// [`DisplayRotation::Deg90`] and [`DisplayRotation::Deg270`] its width is
+    /// the height of the display and the other way around.
+    ///
+    /// The display is expected in [`CoordinateSpace::Logical`], as returned by
+    /// [`get_displays`].
+    pub fn transform_matrix(&self) -> [f64; 6] {
+        let scale = self.scale_factor;
+        let width = f64::from(self.size.width) * scale;
+        let height = f64::from(self.size.height) * scale;
+
+        match self.rotation {
+            DisplayRotation::Deg0 => [scale, 0.0, 0.0, scale, 0.0, 0.0],
+            // The top-left corner of the content is at the top-right of the panel.
+            DisplayRotation::Deg90 => [0.0, scale, -scale, 0.0, height, 0.0],
+            DisplayRotation::Deg180 => [-scale, 0.0, 0.0, -scale, width, height],
+            // The top-left corner of the content is at the bottom-left of the panel.
+            DisplayRotation::Deg270 => [0.0, -scale, scale, 0.0, 0.0, width],
+        }
     }
 
-    #[cfg(target_os = "windows")]
-    pub fn windows_display_observer(&self) -> &PlatformDisplayObserver {
-        &self.inner
+    /// Get the scale factor as a percentage, e.g. `125` for a scale factor of
+    /// `1.25`, as shown by the display settings.
+    ///
+    /// Scale factors derived from pixel counts may be slightly off, so values
+    /// close to one of [`ScaleFactor::STANDARD`] are snapped to it to avoid
+    /// showing `124%`. Custom scale factors are rounded to the nearest percent.
+    pub fn scale_percent(&self) -> u32 {
+        const SNAP_TOLERANCE: f64 = 0.01;
+
+        let standard = ScaleFactor::nearest_standard(self.scale_factor);
+        let scale_factor = if (standard - self.scale_factor).abs() <= SNAP_TOLERANCE {
+            standard
+        } else {
+            self.scale_factor
+        };
+
+        (scale_factor * 100.0).round() as u32
     }
 
-    #[cfg(target_os = "macos")]
-    pub fn macos_display_observer(&self) -> &PlatformDisplayObserver {
-        &self.inner
+    /// Whether the other display has the same size and scale factor as this one.
+    ///
+    /// The scale factors are compared with a small tolerance, since they are
+    /// derived from pixel counts on some platforms.
+    pub fn same_resolution_as(&self, other: &Display) -> bool {
+        const SCALE_FACTOR_EPSILON: f64 = 1e-3;
+
+        self.size == other.size
+            && (self.scale_factor - other.scale_factor).abs() < SCALE_FACTOR_EPSILON
     }
 
-    /// Sets the callback function to be invoked when a display event occurs.
-    pub fn set_callback<F>(&self, callback: F)
-    where
-        F: FnMut(Event) + Send + 'static,
-    {
-        self.inner.set_callback(Box::new(callback));
+    /// Get the display next to this one in the given direction.
+    ///
+    /// Of the displays in `all` that lie entirely on that side of this display
+    /// and overlap it on the perpendicular axis, the nearest one is returned.
+    /// If several are equally near, the one sharing the longest edge wins.
+    /// Displays that are only diagonal to this one are never returned.
+    pub fn neighbor(&self, direction: Direction, all: &[Display]) -> Option<DisplayId> {
+        let (left, top, right, bottom) = edges(self.origin, self.size);
+
+        all.iter()
+            .filter(|other| other.id != self.id)
+            .filter_map(|other| {
+                let (other_left, other_top, other_right, other_bottom) =
+                    edges(other.origin, other.size);
+
+                let (distance, overlap) = match direction {
+                    Direction::Left => (
+                        left - other_right,
+                        bottom.min(other_bottom) - top.max(other_top),
+                    ),
+                    Direction::Right => (
+                        other_left - right,
+                        bottom.min(other_bottom) - top.max(other_top),
+                    ),
+                    Direction::Up => (
+                        top - other_bottom,
+                        right.min(other_right) - left.max(other_left),
+                    ),
+                    Direction::Down => (
+                        other_top - bottom,
+                        right.min(other_right) - left.max(other_left),
+                    ),
+                };
+
+                (distance >= 0 && overlap > 0).then_some((distance, overlap, &other.id))
+            })
+            .min_by(|(a_distance, a_overlap, _), (b_distance, b_overlap, _)| {
+                a_distance.cmp(b_distance).then(b_overlap.cmp(a_overlap))
+            })
+            .map(|(_, _, id)| id.clone())
     }
 
-    /// Removes the currently set callback function. After calling this, no display events will be dispatched.
-    pub fn remove_callback(&self) {
-        self.inner.remove_callback();
+    /// Get the rotation the GPU actually scans out the image with, in degrees,
+    /// if it differs from [`Display::rotation`].
+    ///
+    /// This is useful for capture tools, whose captured frames would otherwise
+    /// end up sideways on setups where the two don't match.
+    ///
+    /// Returns `None` when it matches [`Display::rotation`] or can't be determined.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: Sourced from the rotation of the display path.
+    /// - **macOS**: Always `None`, since Core Graphics only reports a single rotation.
+    pub fn scanout_rotation(&self) -> Option<f64> {
+        #[cfg(target_os = "windows")]
+        {
+            windows::get_scanout_rotation(self.id.windows_id())
+                .filter(|&rotation| rotation != self.rotation)
+                .map(DisplayRotation::degrees)
+        }
+        #[cfg(target_os = "macos")]
+        {
+            None
+        }
     }
 
-    /// Run the event loop.
-    /// Since macOS ui thread must be on main, this function must be called on main thread.
-    /// If you call this on non-main thread, this will panic on macOS.
-    pub fn run(&self) -> Result<(), Error> {
+    /// Get the name of the display as shown in the display settings, e.g.
+    /// `DELL U2720Q`, for a monitor picker.
+    ///
+    /// Returns `None` if the display has no name, e.g. because it's virtual.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: The friendly name of the monitor at the target of the display
+    ///   path, or the product name from the EDID if Windows has none.
+    /// - **macOS**: The localized name of the `NSScreen` of the display. Off the
+    ///   main thread, where `NSScreen` isn't available, the product name from
+    ///   the EDID is used instead, which built-in panels don't have.
+    pub fn friendly_name(&self) -> Option<String> {
         #[cfg(target_os = "windows")]
         {
-            self.inner.run()?;
-            Ok(())
+            windows::get_friendly_name(self.id.windows_id())
         }
         #[cfg(target_os = "macos")]
         {
-            self.inner.run();
-            Ok(())
+            macos::get_friendly_name(*self.id.macos_id())
+        }
+    }
+
+    /// Read the EDID of the display, or `None` if it's unavailable.
+    fn edid(&self) -> Option<Edid> {
+        #[cfg(target_os = "windows")]
+        {
+            windows::get_edid(self.id.windows_id())
+        }
+        #[cfg(target_os = "macos")]
+        {
+            macos::get_edid(*self.id.macos_id())
+        }
+    }
+
+    /// Get the manufacture date of the display from its EDID as the year and,
+    /// if specified, the week of the year.
+    ///
+    /// Returns `None` if the EDID is unavailable or only holds the model year.
+    pub fn manufacture_date(&self) -> Option<(u16, Option<u8>)> {
+        self.edid()?.manufacture_date()
+    }
+
+    /// Get the serial number of the display from the header of its EDID.
+    ///
+    /// Many monitors only fill in one of this and [`Display::serial_string`].
+    /// Returns `None` if the EDID is unavailable or the serial number is unset.
+    pub fn serial_number(&self) -> Option<u32> {
+        self.edid()?.serial_number()
+    }
+
+    /// Get the serial number of the display from the serial number descriptor
+    /// of its EDID, as printed on the label of many monitors.
+    ///
+    /// Returns `None` if the EDID is unavailable or has no such descriptor.
+    pub fn serial_string(&self) -> Option<String> {
+        self.edid()?.serial_string()
+    }
+
+    /// Get the pixel format the display is currently driven with, e.g. to
+    /// detect chroma subsampling forced by the bandwidth of an HDMI link.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: Read from the advanced color information of the display
+    ///   configuration. Returns `None` if the display can't be found.
+    /// - **macOS**: Always `None`, as the pixel format isn't exposed.
+    pub fn pixel_format(&self) -> Option<PixelFormat> {
+        #[cfg(target_os = "windows")]
+        {
+            windows::get_pixel_format(self.id.windows_id())
+        }
+        #[cfg(target_os = "macos")]
+        {
+            None
+        }
+    }
+
+    /// Get the native chromaticity of the panel from its EDID.
+    ///
+    /// This is the intrinsic characterization of the panel, which the color
+    /// profile assigned to the display may override.
+    ///
+    /// Returns `None` if the EDID is unavailable.
+    pub fn chromaticity(&self) -> Option<Chromaticity> {
+        self.edid().map(|edid| edid.chromaticity())
+    }
+
+    /// Get the rectangles of the physical panels a tiled display is made of,
+    /// e.g. to keep content from straddling the bezels.
+    ///
+    /// The tiles are laid out on an even grid over the display, in the same
+    /// coordinates as [`Display::origin`], row by row from the top-left one.
+    /// Returns `None` if the display is a single panel or the EDID is unavailable.
+    ///
+    /// The grid is read from the Tiled Display Topology data block of the
+    /// DisplayID extension of the EDID. Spanned setups built by the driver,
+    /// like NVIDIA Surround or AMD Eyefinity, are only covered when the driver
+    /// reports such a block.
+    pub fn tiles(&self) -> Result<Option<Vec<Rect>>, Error> {
+        let Some((columns, rows)) = self.edid().and_then(|edid| edid.tile_grid()) else {
+            return Ok(None);
+        };
+        if columns * rows <= 1 {
+            return Ok(None);
+        }
+
+        let size = LogicalSize::new(
+            f64::from(self.size.width) / f64::from(columns),
+            f64::from(self.size.height) / f64::from(rows),
+        );
+        let tiles = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| Rect {
+                origin: LogicalPosition::new(
+                    f64::from(self.origin.x) + size.width * f64::from(column),
+                    f64::from(self.origin.y) + size.height * f64::from(row),
+                ),
+                size,
+            })
+            .collect();
+
+        Ok(Some(tiles))
+    }
+
+    /// Get the variable refresh rate range of the display as `(min, max)` in Hz,
+    /// e.g. `(48.0, 144.0)`.
+    ///
+    /// Returns `None` if the display doesn't advertise variable refresh rate or
+    /// the EDID is unavailable. The range is read from the AMD vendor-specific
+    /// data block of the CTA-861 extension (FreeSync) or the Adaptive-Sync data
+    /// block of the DisplayID extension, as for [`Display::vrr`].
+    pub fn vrr_range(&self) -> Result<Option<(f64, f64)>, Error> {
+        Ok(self.edid().and_then(|edid| edid.vrr_range()))
+    }
+
+    /// Get the diagonal size of the display in inches, e.g. `27.0` for a 27-inch monitor.
+    ///
+    /// Returns `None` if the physical size of the display is unknown.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: The physical size is read from the EDID.
+    /// - **macOS**: The physical size is the value of [`CGDisplayScreenSize`][CGDisplayScreenSize],
+    ///   or read from the EDID if that's unknown.
+    ///
+    /// [CGDisplayScreenSize]: https://developer.apple.com/documentation/coregraphics/cgdisplayscreensize(_:)?language=objc
+    pub fn diagonal_inches(&self) -> Option<f64> {
+        const MILLIMETERS_PER_INCH: f64 = 25.4;
+
+        #[cfg(target_os = "windows")]
+        let (width, height) = self.edid()?.physical_size_mm()?;
+        #[cfg(target_os = "macos")]
+        let (width, height) = macos::get_physical_size_mm(*self.id.macos_id())?;
+
+        Some(width.hypot(height) / MILLIMETERS_PER_INCH)
+    }
+
+    /// Get the multiplier applied on top of [`Display::scale_factor`] by the
+    /// accessibility settings, e.g. `1.5` if text is enlarged to 150%.
+    ///
+    /// This is a system-wide setting, so it's the same for every display.
+    /// Returns `None` if it can't be determined.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: The text scale of "Make text bigger" in the accessibility settings.
+    /// - **macOS**: The factor of the accessibility zoom, or `1.0` while it's
+    ///   disabled. The factor is read from the `com.apple.universalaccess`
+    ///   preferences, so this is `None` while the zoom is enabled if they can't
+    ///   be read, e.g. in a sandboxed app. The "Larger Text" option of the
+    ///   display settings is a scaled resolution, which shows in
+    ///   [`Display::size`] instead. The text size of the accessibility settings
+    ///   only applies to the apps that support it, so it isn't reflected.
+    pub fn accessibility_scale(&self) -> Option<f64> {
+        #[cfg(target_os = "windows")]
+        {
+            windows::get_accessibility_scale()
+        }
+        #[cfg(target_os = "macos")]
+        {
+            macos::get_accessibility_scale()
+        }
+    }
+
+    /// Get the software brightness of the display, from `0.0` to `1.0`.
+    ///
+    /// Returns `None` if the brightness can't be controlled by software.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: Read from the monitor over DDC/CI, which most desktop monitors
+    ///   support but laptop panels don't. This may block for tens of milliseconds.
+    ///   See `windows::get_monitor_brightness` for the range of the monitor.
+    /// - **macOS**: Read with the private DisplayServices framework, which supports
+    ///   the built-in display and Apple displays.
+    pub fn brightness(&self) -> Option<f32> {
+        #[cfg(target_os = "windows")]
+        {
+            windows::get_monitor_brightness(self.id.windows_id())
+                .ok()?
+                .normalized()
+        }
+        #[cfg(target_os = "macos")]
+        {
+            macos::get_brightness(*self.id.macos_id())
+        }
+    }
+
+    /// Set the software brightness of the display, from `0.0` to `1.0`.
+    ///
+    /// The brightness is clamped to that range.
+    ///
+    /// # Errors
+    /// Returns [`Error::PlatformError`] if the brightness can't be controlled
+    /// by software. See [`Display::brightness`] for the supported displays.
+    pub fn set_brightness(&self, brightness: f32) -> Result<(), Error> {
+        #[cfg(target_os = "windows")]
+        {
+            let id = self.id.windows_id();
+            let value = windows::get_monitor_brightness(id)?.denormalize(brightness);
+            Ok(windows::set_monitor_brightness(id, value)?)
+        }
+        #[cfg(target_os = "macos")]
+        {
+            Ok(macos::set_brightness(*self.id.macos_id(), brightness)?)
+        }
+    }
+
+    /// Get the active input source of the monitor, as the value of the DDC/CI
+    /// VCP code `0x60`, e.g. `0x11` for HDMI 1.
+    ///
+    /// Returns `None` if the monitor can't be controlled over DDC/CI.
+    ///
+    /// # Errors
+    /// Returns [`Error::PlatformError`] if the display can't be found.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: Read with the monitor configuration API. This may block for
+    ///   tens of milliseconds.
+    /// - **macOS**: Always `None`, as DDC/CI is only reachable through private
+    ///   I2C interfaces that differ between Intel and Apple silicon Macs.
+    pub fn input_source(&self) -> Result<Option<u8>, Error> {
+        #[cfg(target_os = "windows")]
+        {
+            Ok(windows::get_monitor_input_source(self.id.windows_id())?)
+        }
+        #[cfg(target_os = "macos")]
+        {
+            Ok(None)
+        }
+    }
+
+    /// Switch the monitor to another input source, as the value of the DDC/CI
+    /// VCP code `0x60`. See [`Display::input_source`].
+    ///
+    /// Switching away from the input of this machine usually makes the display
+    /// disappear from it.
+    ///
+    /// # Errors
+    /// Returns [`Error::PlatformError`] if the monitor can't be controlled over DDC/CI.
+    pub fn set_input_source(&self, source: u8) -> Result<(), Error> {
+        #[cfg(target_os = "windows")]
+        {
+            Ok(windows::set_monitor_input_source(
+                self.id.windows_id(),
+                source,
+            )?)
+        }
+        #[cfg(target_os = "macos")]
+        {
+            _ = source;
+            Err(Error::PlatformError(PlatformError::Unsupported {
+                operation: "set_input_source",
+            }))
+        }
+    }
+
+    /// Get the number of displays showing the same content as this one,
+    /// including itself, e.g. for a "mirrored to 3 displays" badge.
+    ///
+    /// Returns `1` if the display isn't mirrored.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: The number of monitors in the clone set of the display.
+    /// - **macOS**: The number of active displays mirroring the same display,
+    ///   together with that display.
+    pub fn mirror_set_size(&self) -> Result<usize, Error> {
+        #[cfg(target_os = "windows")]
+        {
+            Ok(windows::get_mirror_set_size(self.id.windows_id())?)
+        }
+        #[cfg(target_os = "macos")]
+        {
+            Ok(macos::get_mirror_set_size(*self.id.macos_id())?)
+        }
+    }
+
+    /// Get the modes the display can be set to, sorted by size and refresh rate.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: The modes enumerated by [`EnumDisplaySettingsW`][EnumDisplaySettingsW],
+    ///   whose sizes are in pixels.
+    /// - **macOS**: The modes returned by [`CGDisplayCopyAllDisplayModes`][CGDisplayCopyAllDisplayModes],
+    ///   whose sizes are in points.
+    ///
+    /// [EnumDisplaySettingsW]: https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-enumdisplaysettingsw
+    /// [CGDisplayCopyAllDisplayModes]: https://developer.apple.com/documentation/coregraphics/cgdisplaycopyalldisplaymodes(_:_:)?language=objc
+    pub fn available_modes(&self) -> Result<Vec<DisplayMode>, Error> {
+        #[cfg(target_os = "windows")]
+        {
+            Ok(windows::get_available_modes(self.id.windows_id()))
+        }
+        #[cfg(target_os = "macos")]
+        {
+            Ok(macos::get_available_modes(*self.id.macos_id())?)
+        }
+    }
+
+    /// Check whether the display has a mode of the given size and refresh rate,
+    /// e.g. to validate a resolution before switching to it.
+    ///
+    /// A refresh rate of `None` accepts any refresh rate at that size. Refresh
+    /// rates are compared with a tolerance of 0.01 Hz, so 59.94 Hz and 60 Hz
+    /// are told apart. Modes without a fixed refresh rate, like the ones of
+    /// built-in panels on macOS, accept any refresh rate.
+    pub fn supports_mode(
+        &self,
+        size: LogicalSize<u32>,
+        refresh_rate: Option<f64>,
+    ) -> Result<bool, Error> {
+        const REFRESH_RATE_EPSILON: f64 = 0.01;
+
+        Ok(self.available_modes()?.iter().any(|mode| {
+            mode.size == size
+                && match (refresh_rate, mode.refresh_rate) {
+                    (Some(expected), Some(actual)) => {
+                        (expected - actual).abs() < REFRESH_RATE_EPSILON
+                    }
+                    _ => true,
+                }
+        }))
+    }
+
+    /// Get the modes of the display that can carry HDR content.
+    ///
+    /// See [`DisplayMode::hdr_capable`] for how this is determined.
+    pub fn hdr_modes(&self) -> Result<Vec<DisplayMode>, Error> {
+        let mut modes = self.available_modes()?;
+        modes.retain(|mode| mode.hdr_capable);
+        Ok(modes)
+    }
+
+    /// Get the smallest and the largest resolution the display supports, by area.
+    ///
+    /// If the modes can't be enumerated beyond the current one, both are the
+    /// current size of the display.
+    pub fn resolution_range(&self) -> Result<(LogicalSize<u32>, LogicalSize<u32>), Error> {
+        let area = |size: &LogicalSize<u32>| size.width as u64 * size.height as u64;
+        let sizes = self.available_modes()?.into_iter().map(|mode| mode.size);
+
+        let min = sizes.clone().min_by_key(area).unwrap_or(self.size);
+        let max = sizes.max_by_key(area).unwrap_or(self.size);
+
+        Ok((min, max))
+    }
+
+    /// Get the modes whose aspect ratio is within `tolerance` of the native one,
+    /// leaving out the stretched and letterboxed modes.
+    ///
+    /// The native aspect ratio is the one of the largest mode, which is the
+    /// native resolution of the panel. The ratios are compared as width divided
+    /// by height, so a tolerance of `0.01` keeps 16:9 (1.778) but not 16:10 (1.6).
+    pub fn available_modes_matching_aspect(
+        &self,
+        tolerance: f64,
+    ) -> Result<Vec<DisplayMode>, Error> {
+        let aspect = |size: &LogicalSize<u32>| size.width as f64 / size.height as f64;
+        let area = |size: &LogicalSize<u32>| size.width as u64 * size.height as u64;
+
+        let mut modes = self.available_modes()?;
+        let native = modes
+            .iter()
+            .map(|mode| mode.size)
+            .max_by_key(area)
+            .unwrap_or(self.size);
+        let native_aspect = aspect(&native);
+
+        modes.retain(|mode| (aspect(&mode.size) - native_aspect).abs() <= tolerance);
+        Ok(modes)
+    }
+}
+
+/// The chromaticity of a display, as CIE 1931 `(x, y)` coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Chromaticity {
+    /// The red primary.
+    pub red: (f64, f64),
+    /// The green primary.
+    pub green: (f64, f64),
+    /// The blue primary.
+    pub blue: (f64, f64),
+    /// The white point.
+    pub white: (f64, f64),
+    /// The gamma of the display, or `None` if it's only defined in an EDID extension.
+    pub gamma: Option<f64>,
+}
+
+/// A rectangle in the display arrangement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rect {
+    /// The top-left corner of the rectangle.
+    pub origin: LogicalPosition<f64>,
+    /// The size of the rectangle.
+    pub size: LogicalSize<f64>,
+}
+
+/// A direction in the display arrangement, used by [`Display::neighbor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// The input capabilities of a display.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InputCapabilities {
+    /// Whether the display accepts touch input.
+    pub touch: bool,
+    /// Whether the display accepts pen input.
+    pub pen: bool,
+}
+
+bitflags::bitflags! {
+    /// The capabilities of a display as a set of flags, see [`Display::capabilities`].
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct Capabilities: u32 {
+        /// The display can show HDR content, see [`DisplayMode::hdr_capable`].
+        const HDR = 1 << 0;
+        /// The display supports variable refresh rate, see [`VrrInfo::supported`].
+        const VRR = 1 << 1;
+        /// See [`Display::is_builtin`].
+        const BUILTIN = 1 << 2;
+        /// See [`InputCapabilities::touch`].
+        const TOUCH = 1 << 3;
+        /// See [`InputCapabilities::pen`].
+        const PEN = 1 << 4;
+        /// See [`Display::is_mirrored`].
+        const MIRRORED = 1 << 5;
+        /// See [`Display::is_primary`].
+        const PRIMARY = 1 << 6;
+    }
+}
+
+/// A mode a display can be set to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DisplayMode {
+    /// The resolution of the mode, in the same unit as [`Display::size`].
+    pub size: LogicalSize<u32>,
+    /// The refresh rate of the mode in Hz.
+    ///
+    /// This is `None` if the platform reports no fixed refresh rate, as is
+    /// common for built-in panels on macOS.
+    pub refresh_rate: Option<f64>,
+    /// The refresh rate of the mode as an exact fraction.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: The system reports whole numbers only, truncating e.g.
+    ///   59.94 Hz to 59 Hz. Such rates just below a common rate are assumed to be
+    ///   NTSC rates, so `59` becomes `60000/1001`.
+    /// - **macOS**: Derived from the refresh rate of the mode timing, recognizing
+    ///   the NTSC rates.
+    pub exact_refresh_rate: Option<RefreshRate>,
+    /// Whether the mode can carry HDR content.
+    ///
+    /// Neither platform reports this per mode, so it's the HDR capability of
+    /// the display applied to all of its modes.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: Whether advanced color is supported by the display.
+    /// - **macOS**: Whether the `NSScreen` of the display has a potential EDR
+    ///   headroom. `NSScreen` is only available on the main thread, so this is
+    ///   always `false` when the modes are enumerated on another thread.
+    pub hdr_capable: bool,
+}
+
+/// A refresh rate as an exact fraction of Hz, e.g. `60000/1001` for 59.94 Hz.
+///
+/// This is meant for frame pacing and A/V sync, where rounding 59.94 Hz to 60 Hz
+/// drifts by a frame every 16.7 seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RefreshRate {
+    /// The number of frames per `denominator` seconds.
+    pub numerator: u32,
+    /// The number of seconds the `numerator` frames take.
+    pub denominator: u32,
+}
+
+impl RefreshRate {
+    /// The whole rates that also exist as NTSC rates, slowed down by `1000/1001`.
+    #[cfg(target_os = "windows")]
+    const NTSC_BASE_RATES: &[u32] = &[24, 30, 48, 60, 72, 96, 120, 144, 240];
+
+    /// Get the refresh rate in Hz.
+    pub fn as_f64(&self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+
+    /// Get the exact rate of a rate in Hz, recognizing whole and NTSC rates.
+    ///
+    /// Other rates are kept to the millihertz. Returns `None` if the rate
+    /// isn't positive.
+    pub fn from_hz(hz: f64) -> Option<Self> {
+        const TOLERANCE: f64 = 1e-2;
+
+        if !hz.is_finite() || hz <= 0.0 {
+            return None;
+        }
+
+        let whole = hz.round();
+        if (hz - whole).abs() < TOLERANCE {
+            return Some(Self::whole(whole as u32));
+        }
+
+        let ntsc_base = (hz * 1001.0 / 1000.0).round();
+        if (hz - ntsc_base * 1000.0 / 1001.0).abs() < TOLERANCE {
+            return Some(Self::ntsc(ntsc_base as u32));
+        }
+
+        Some(Self {
+            numerator: (hz * 1000.0).round() as u32,
+            denominator: 1000,
+        })
+    }
+
+    /// Get the exact rate of a rate in Hz that was truncated to a whole number.
+    ///
+    /// A rate just below a common rate is assumed to be its NTSC rate.
+    #[cfg(target_os = "windows")]
+    pub(crate) fn from_truncated_hz(hz: u32) -> Self {
+        if Self::NTSC_BASE_RATES.contains(&(hz + 1)) {
+            Self::ntsc(hz + 1)
+        } else {
+            Self::whole(hz)
+        }
+    }
+
+    fn whole(hz: u32) -> Self {
+        Self {
+            numerator: hz,
+            denominator: 1,
+        }
+    }
+
+    fn ntsc(base_hz: u32) -> Self {
+        Self {
+            numerator: base_hz * 1000,
+            denominator: 1001,
+        }
+    }
+}
+
+/// Sort the modes by size and refresh rate and remove the duplicates.
+pub(crate) fn sort_modes(modes: &mut Vec<DisplayMode>) {
+    let key = |mode: &DisplayMode| (mode.size.width, mode.size.height);
+    modes.sort_by(|a, b| {
+        key(a).cmp(&key(b)).then(
+            a.refresh_rate
+                .partial_cmp(&b.refresh_rate)
+                .unwrap_or(Ordering::Equal),
+        )
+    });
+    modes.dedup();
+}
+
+/// The pixel format a display is currently driven with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PixelFormat {
+    /// How the colors are encoded on the link to the display.
+    pub encoding: ColorEncoding,
+    /// The number of bits per color channel, e.g. `8` or `10`.
+    pub bits_per_channel: u32,
+}
+
+/// The color encoding of the signal sent to a display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorEncoding {
+    /// RGB, with every channel at full resolution.
+    Rgb,
+    /// YCbCr with full chroma resolution.
+    YCbCr444,
+    /// YCbCr with the chroma halved horizontally.
+    YCbCr422,
+    /// YCbCr with the chroma halved in both directions, which blurs colored text.
+    YCbCr420,
+    /// A single intensity channel, as for monochrome displays.
+    Intensity,
+}
+
+/// The arrangement of the subpixels of a display.
+///
+/// The layout is read from the EDID, which rarely specifies it, so
+/// [`SubpixelLayout::Unknown`] is common.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SubpixelLayout {
+    /// Red, green and blue subpixels from left to right.
+    Rgb,
+    /// Blue, green and red subpixels from left to right.
+    Bgr,
+    /// Red, green and blue subpixels from top to bottom.
+    VerticalRgb,
+    /// Blue, green and red subpixels from top to bottom.
+    VerticalBgr,
+    /// The subpixels aren't arranged in stripes, so subpixel rendering doesn't apply.
+    None,
+    /// The EDID doesn't specify the layout.
+    #[default]
+    Unknown,
+}
+
+/// The variable refresh rate capability of a display.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VrrInfo {
+    /// Whether the display advertises support for variable refresh rate.
+    pub supported: bool,
+    /// The minimum refresh rate of the variable refresh rate range, in Hz.
+    pub min_hz: Option<f64>,
+    /// The maximum refresh rate of the variable refresh rate range, in Hz.
+    pub max_hz: Option<f64>,
+}
+
+/// The rotation of a display, clockwise.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DisplayRotation {
+    /// Not rotated.
+    #[default]
+    Deg0,
+    /// Rotated by 90 degrees.
+    Deg90,
+    /// Rotated by 180 degrees.
+    Deg180,
+    /// Rotated by 270 degrees.
+    Deg270,
+}
+
+impl DisplayRotation {
+    /// Get the rotation nearest to the given angle in degrees.
+    pub fn from_degrees(degrees: f64) -> Self {
+        match ((degrees / 90.0).round() as i64).rem_euclid(4) {
+            1 => Self::Deg90,
+            2 => Self::Deg180,
+            3 => Self::Deg270,
+            _ => Self::Deg0,
+        }
+    }
+
+    /// Get the rotation in degrees.
+    pub fn degrees(self) -> f64 {
+        match self {
+            Self::Deg0 => 0.0,
+            Self::Deg90 => 90.0,
+            Self::Deg180 => 180.0,
+            Self::Deg270 => 270.0,
+        }
+    }
+}
+
+/// Helpers for the standard scale factors used by the operating system.
+pub struct ScaleFactor;
+
+impl ScaleFactor {
+    /// The standard scale factors supported by the platform, in ascending order.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: The steps offered by the display settings, from 100% to 400%.
+    /// - **macOS**: The backing scale factors. Scaled ("looks like") resolutions
+    ///   are still rendered with one of these backing scale factors, which is
+    ///   what [`Display::scale_factor`] reports.
+    pub const STANDARD: &'static [f64] = PLATFORM_STANDARD_SCALE_FACTORS;
+
+    /// Snap the given scale factor to the nearest one in [`ScaleFactor::STANDARD`].
+    ///
+    /// If the value is exactly between two steps, the smaller one is returned.
+    pub fn nearest_standard(scale_factor: f64) -> f64 {
+        Self::STANDARD
+            .iter()
+            .copied()
+            .min_by(|a, b| {
+                (a - scale_factor)
+                    .abs()
+                    .total_cmp(&(b - scale_factor).abs())
+            })
+            .unwrap_or(scale_factor)
+    }
+}
+
+/// An event that occurs when the display configuration changes.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Event {
+    /// A display was added.
+    Added {
+        display: Display,
+        /// Why the display was added.
+        reason: ChangeReason,
+    },
+    /// A display was removed.
+    Removed {
+        id: DisplayId,
+        /// Why the display was removed.
+        reason: ChangeReason,
+    },
+    /// The size of a display changed.
+    SizeChanged {
+        display: Display,
+        before: LogicalSize<u32>,
+        after: LogicalSize<u32>,
+    },
+    /// The origin of a display changed.
+    OriginChanged {
+        display: Display,
+        before: LogicalPosition<i32>,
+        after: LogicalPosition<i32>,
+        /// Whether the origin changed only because the primary display moved.
+        ///
+        /// Coordinates are relative to the primary display, so when the primary
+        /// display is moved in the arrangement, every other display shifts by the
+        /// same offset even though it didn't move relative to the others.
+        caused_by_primary_shift: bool,
+    },
+    /// A display was mirrored.
+    Mirrored(Display),
+    /// A display was unmirrored.
+    UnMirrored(Display),
+    /// The color profile assigned to a display changed.
+    ///
+    /// Color-managed apps should reload the profile of the display.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: Checked when a system color, setting or the display
+    ///   configuration changes, by comparing the ICM profile of each display.
+    /// - **macOS**: Checked on [`NSScreenColorSpaceDidChangeNotification`][notification],
+    ///   by comparing the ICC data of the color space of each display. This
+    ///   requires the [`NSApplication`][NSApplication] event loop to be running.
+    ///
+    /// [notification]: https://developer.apple.com/documentation/appkit/nsscreen/colorspacedidchangenotification?language=objc
+    /// [NSApplication]: https://developer.apple.com/documentation/appkit/nsapplication
+    ColorProfileChanged { display: Display },
+    /// A display was removed and added back shortly after, e.g. by a KVM switch
+    /// or a dock.
+    ///
+    /// This is only produced in place of [`Event::Removed`] and [`Event::Added`]
+    /// once enabled with [`DisplayObserver::set_reconnect_window`].
+    Reconnected { display: Display },
+    /// The display mirrored by a display changed while it stayed mirrored,
+    /// e.g. when another display of the mirror set becomes the original.
+    ///
+    /// See [`Display::mirror_source`]. This is only produced on macOS, since
+    /// the mirror source is always `None` on Windows.
+    MirrorSourceChanged {
+        display: Display,
+        before: Option<DisplayId>,
+        after: Option<DisplayId>,
+    },
+    /// The top-left corner of the bounding box of all displays moved, e.g.
+    /// because a display was added to the left of the others or the primary
+    /// display changed.
+    ///
+    /// Absolute positions cached by the app, such as window positions, may be
+    /// off by the difference between both.
+    VirtualOriginChanged {
+        before: LogicalPosition<i32>,
+        after: LogicalPosition<i32>,
+    },
+    /// The last display was removed, e.g. when a remote desktop session was
+    /// disconnected, so the system is now headless.
+    ///
+    /// This follows the [`Event::Removed`] of the last display. With
+    /// [`DisplayObserver::set_reconnect_window`], it's held back together with
+    /// that removal, and neither is dispatched if a display comes back in time.
+    AllDisplaysRemoved,
+    /// A display was added while the system was headless, or after the
+    /// observer was created without any display.
+    ///
+    /// This precedes the [`Event::Added`] of the display.
+    DisplaysRestored,
+    /// The display became or stopped being the primary display, see
+    /// [`Display::is_primary`].
+    ///
+    /// Switching the primary display usually produces two of these at once,
+    /// one for the old and one for the new primary display.
+    PrimaryChanged { display: Display, was_primary: bool },
+    /// The refresh rate of a display changed, e.g. from 60 Hz to 120 Hz.
+    ///
+    /// See [`Display::refresh_rate`].
+    RefreshRateChanged {
+        display: Display,
+        before: Option<f64>,
+        after: Option<f64>,
+    },
+    /// The rotation of a display changed, e.g. when a kiosk display was
+    /// turned to portrait.
+    ///
+    /// Rotating by 90 or 270 degrees also swaps the width and height of the
+    /// display. The [`Event::SizeChanged`] of that always follows this event
+    /// in the same reconfiguration.
+    RotationChanged {
+        display: Display,
+        before: DisplayRotation,
+        after: DisplayRotation,
+    },
+}
+
+impl Event {
+    /// Get the id of the display the event is about, e.g. to route events per
+    /// display.
+    ///
+    /// Returns `None` for [`Event::VirtualOriginChanged`], [`Event::AllDisplaysRemoved`]
+    /// and [`Event::DisplaysRestored`], which are about all displays.
+    pub fn display_id(&self) -> Option<&DisplayId> {
+        match self {
+            Self::Removed { id, .. } => Some(id),
+            Self::Added { display, .. }
+            | Self::SizeChanged { display, .. }
+            | Self::OriginChanged { display, .. }
+            | Self::Mirrored(display)
+            | Self::UnMirrored(display)
+            | Self::ColorProfileChanged { display }
+            | Self::Reconnected { display }
+            | Self::MirrorSourceChanged { display, .. }
+            | Self::PrimaryChanged { display, .. }
+            | Self::RefreshRateChanged { display, .. }
+            | Self::RotationChanged { display, .. } => Some(&display.id),
+            Self::VirtualOriginChanged { .. }
+            | Self::AllDisplaysRemoved
+            | Self::DisplaysRestored => None,
+        }
+    }
+}
+
+/// An [`Event`] numbered in the order it was dispatched, passed to the callback
+/// set with [`DisplayObserver::set_sequenced_callback`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SequencedEvent {
+    /// The number of the event, increasing by one for every event.
+    pub seq: u64,
+    /// The event itself.
+    pub event: Event,
+}
+
+/// Why a display was added or removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChangeReason {
+    /// The monitor was physically connected or disconnected, or turned on or off.
+    Hotplug,
+    /// The display was added or removed as part of a mode change, like a
+    /// display turning into or out of a mirror of another one.
+    ModeChange,
+    /// The display was enabled or disabled in the display settings while
+    /// staying connected, e.g. when switching the projection mode.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: Monitor arrivals and removals are told apart by the device
+    ///   notifications received before the configuration changes. A change not
+    ///   preceded by one is reported as this.
+    Arrangement,
+}
+
+/// The number of events of a single reconfiguration kept on the stack.
+///
+/// This is sized so that a change touching every display of a large
+/// multi-monitor wall (e.g. all origins shifting) doesn't spill to the heap.
+const EVENT_BATCH_INLINE_CAPACITY: usize = 16;
+
+/// The events produced by a single reconfiguration.
+pub(crate) type EventBatch = SmallVec<[Event; EVENT_BATCH_INLINE_CAPACITY]>;
+
+/// A callback function that is called when a display event occurs.
+pub type DisplayEventCallback = Box<dyn FnMut(Event) + Send + 'static>;
+
+/// A callback function that is called once per display reconfiguration, without any details.
+pub type DisplayChangeCallback = Box<dyn FnMut() + Send + 'static>;
+
+/// A function that transforms an event before it is passed to the callback.
+/// Returning `None` suppresses the event.
+pub type DisplayEventMapper = Box<dyn FnMut(Event) -> Option<Event> + Send + 'static>;
+
+/// A display observer that monitors changes to the display configuration.
+pub struct DisplayObserver {
+    inner: PlatformDisplayObserver,
+}
+
+impl From<PlatformDisplayObserver> for DisplayObserver {
+    fn from(inner: PlatformDisplayObserver) -> Self {
+        Self { inner }
+    }
+}
+
+impl From<DisplayObserver> for PlatformDisplayObserver {
+    fn from(value: DisplayObserver) -> Self {
+        value.inner
+    }
+}
+
+/// A builder to configure a [`DisplayObserver`] before creating it.
+#[derive(Debug, Clone, Default)]
+pub struct DisplayObserverBuilder {
+    coordinate_space: CoordinateSpace,
+    watch_hdmi_cec_changes: bool,
+    log_to_os: bool,
+}
+
+impl DisplayObserverBuilder {
+    /// Create a builder with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the coordinate space of the geometry of the displays reported by
+    /// the observer, including the ones in events. Defaults to
+    /// [`CoordinateSpace::Logical`].
+    pub fn coordinate_space(mut self, space: CoordinateSpace) -> Self {
+        self.coordinate_space = space;
+        self
+    }
+
+    /// Sets whether to watch for displays a TV or an AV receiver drops or adds
+    /// back over HDMI-CEC when it's powered off or on. Defaults to `false`.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: Such changes may come without any notification, so the
+    ///   displays are reconciled every two seconds while the observer runs.
+    /// - **macOS**: Has no effect, since CoreGraphics already reports these changes.
+    pub fn watch_hdmi_cec_changes(mut self, enabled: bool) -> Self {
+        self.watch_hdmi_cec_changes = enabled;
+        self
+    }
+
+    /// Sets whether every event is also written to the log of the OS, for
+    /// administrators inspecting the OS logs of a headless service. Defaults
+    /// to `false`.
+    ///
+    /// The events are logged when they're delivered, before
+    /// [`DisplayObserver::set_event_mapper`] and any callback. The events held
+    /// back by [`DisplayObserver::set_reconnect_window`] or
+    /// [`DisplayObserver::suspend`] are only logged once they're released.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: Written to the Application event log, under the
+    ///   `display-config` source. Event Viewer only shows the text of the events
+    ///   as is if the source is installed with a message file, which needs
+    ///   administrator rights, e.g. once when the app is installed:
+    ///
+    ///   ```text
+    ///   reg add HKLM\SYSTEM\CurrentControlSet\Services\EventLog\Application\display-config ^
+    ///       /v EventMessageFile /t REG_EXPAND_SZ ^
+    ///       /d %SystemRoot%\Microsoft.NET\Framework64\v4.0.30319\EventLogMessages.dll
+    ///   ```
+    ///
+    ///   Otherwise, the text follows a notice that the description of the
+    ///   event can't be found.
+    /// - **macOS**: Written to the unified log through `syslog`.
+    pub fn log_to_os(mut self, enabled: bool) -> Self {
+        self.log_to_os = enabled;
+        self
+    }
+
+    /// Create the display observer.
+    ///
+    /// This succeeds without any display, as in a headless session. The
+    /// observer then dispatches [`Event::DisplaysRestored`] once a display is added.
+    pub fn build(self) -> Result<DisplayObserver, Error> {
+        let inner = PlatformDisplayObserver::with_coordinate_space(self.coordinate_space)?;
+
+        #[cfg(target_os = "windows")]
+        inner.watch_hdmi_cec_changes(self.watch_hdmi_cec_changes)?;
+        inner.set_log_to_os(self.log_to_os);
+
+        Ok(DisplayObserver { inner })
+    }
+}
+
+impl DisplayObserver {
+    /// Create the display observer instance with the default configuration.
+    ///
+    /// This succeeds without any display, as in a headless session. The
+    /// observer then dispatches [`Event::DisplaysRestored`] once a display is added.
+    pub fn new() -> Result<Self, Error> {
+        DisplayObserverBuilder::new().build()
+    }
+
+    /// Create a builder to configure the display observer.
+    pub fn builder() -> DisplayObserverBuilder {
+        DisplayObserverBuilder::new()
+    }
+
+    #[cfg(target_os = "windows")]
+    pub fn windows_display_observer(&self) -> &PlatformDisplayObserver {
+        &self.inner
+    }
+
+    #[cfg(target_os = "macos")]
+    pub fn macos_display_observer(&self) -> &PlatformDisplayObserver {
+        &self.inner
+    }
+
+    /// Get the generation of the display configuration, a counter bumped on
+    /// every reconfiguration that produced events.
+    ///
+    /// Store it and pass it to [`DisplayObserver::changed_since`] later to
+    /// cheaply check whether anything changed in between. This doesn't lock
+    /// the observer, so it's fine to call from inside a callback.
+    pub fn generation(&self) -> u64 {
+        self.inner.generation()
+    }
+
+    /// Whether the display configuration changed since [`DisplayObserver::generation`]
+    /// returned `token`.
+    pub fn changed_since(&self, token: u64) -> bool {
+        self.generation() > token
+    }
+
+    /// Get the generation together with the displays the observer currently knows of,
+    /// sorted by ID.
+    ///
+    /// Both are read atomically, so the list is exactly the state of the
+    /// returned generation. Store both and pass the generation to
+    /// [`DisplayObserver::changed_since`] later to tell if the list is stale.
+    ///
+    /// Unlike [`DisplayObserver::generation`], this locks the observer, so
+    /// don't call it from inside a callback.
+    pub fn snapshot(&self) -> (u64, Vec<Display>) {
+        self.inner.snapshot()
+    }
+
+    /// Gets the displays before and after the last change tracked by the
+    /// observer, each sorted by ID, e.g. to log the full context of an event.
+    ///
+    /// Before the first change, both lists are the initial displays. Like
+    /// [`DisplayObserver::snapshot`], this locks the observer, so don't call it
+    /// from inside a callback.
+    pub fn last_snapshot(&self) -> (Vec<Display>, Vec<Display>) {
+        self.inner.last_snapshot()
+    }
+
+    /// Compare the displays with the ones the observer knows of right away and
+    /// get the events of the difference.
+    ///
+    /// This is meant for the focus or activation handler of a GUI app, to catch
+    /// up on the changes made while it was in the background without waiting for
+    /// the event loop. It can be called from any thread, and doesn't need
+    /// [`DisplayObserver::run`] to be running. The events are also dispatched to
+    /// the callback and the handlers, on the calling thread before this returns.
+    ///
+    /// Like [`DisplayObserver::snapshot`], this locks the observer, so don't call
+    /// it from inside a callback.
+    pub fn reconcile_blocking(&self) -> Result<Vec<Event>, Error> {
+        Ok(self.inner.reconcile_blocking()?)
+    }
+
+    /// Wait for the next event, e.g. to react to a single change in an async app.
+    ///
+    /// The future resolves on the next event passed to the callback, after
+    /// [`DisplayObserver::set_event_mapper`], or to `None` if the observer is
+    /// dropped first. Nothing stays installed once it resolves or is dropped:
+    /// a dropped future is skipped and forgotten by the next dispatch.
+    /// The events are still dispatched by [`DisplayObserver::run`], so it must
+    /// be running, e.g. on the main thread while the future is awaited elsewhere.
+    ///
+    /// It works with any async runtime, since it doesn't depend on one.
+    #[cfg(feature = "async")]
+    pub fn next_event(&self) -> impl Future<Output = Option<Event>> + Send + 'static {
+        self.inner.next_event()
+    }
+
+    /// Sets the callback function to be invoked when a display event occurs.
+    pub fn set_callback<F>(&self, callback: F)
+    where
+        F: FnMut(Event) + Send + 'static,
+    {
+        self.inner.set_callback(Box::new(callback));
+    }
+
+    /// Sets the callback function with a weak reference to the state it updates,
+    /// e.g. the app struct holding the observer.
+    ///
+    /// Capturing the state itself in the callback creates a reference cycle when
+    /// the state owns the observer, so neither is ever dropped. The reference is
+    /// upgraded for every event instead, and the event is skipped once the state
+    /// is gone. This replaces the callback set before.
+    pub fn set_weak_callback<T, F>(&self, target: std::sync::Weak<Mutex<T>>, mut callback: F)
+    where
+        T: Send + 'static,
+        F: FnMut(&mut T, Event) + Send + 'static,
+    {
+        self.inner.set_callback(Box::new(move |event| {
+            if let Some(target) = target.upgrade() {
+                let mut target = target.lock().unwrap_or_else(PoisonError::into_inner);
+                callback(&mut target, event);
+            }
+        }));
+    }
+
+    /// Sets a sink that forwards every event to another event loop, such as the
+    /// one of a GUI framework.
+    ///
+    /// This is [`DisplayObserver::set_callback`] for the common pattern of
+    /// handing events over to another thread, and replaces the callback set
+    /// before. The sink runs while the observer is locked, so it should only
+    /// post the event, e.g. to a channel or an event loop proxy, and return
+    /// quickly. It must not call back into the observer.
+    ///
+    /// The sink usually runs on the thread running [`DisplayObserver::run`]
+    /// (the main thread on macOS). The events dispatched by these functions
+    /// run it on the thread calling them instead:
+    /// - [`DisplayObserver::reconcile_blocking`];
+    /// - [`DisplayObserver::resume`], for the events held back;
+    /// - [`DisplayObserver::set_reconnect_window`] with `None`, for the
+    ///   removals held back.
+    ///
+    /// ```no_run
+    /// use std::sync::mpsc;
+    ///
+    /// fn main() -> Result<(), display_config::Error> {
+    ///     let observer = display_config::DisplayObserver::new()?;
+    ///     let (sender, receiver) = mpsc::channel();
+    ///
+    ///     observer.set_proxy_sender(move |event| {
+    ///         _ = sender.send(event);
+    ///     });
+    ///
+    ///     std::thread::spawn(move || {
+    ///         for event in receiver {
+    ///             println!("{event:?}");
+    ///         }
+    ///     });
+    ///
+    ///     observer.run()
+    /// }
+    /// ```
+    pub fn set_proxy_sender<F>(&self, sender: F)
+    where
+        F: Fn(Event) + Send + 'static,
+    {
+        self.inner.set_callback(Box::new(sender));
+    }
+
+    /// Get a channel receiving every event, e.g. to handle the events with
+    /// `for event in receiver` on a thread of your own while
+    /// [`DisplayObserver::run`] runs on the main thread.
+    ///
+    /// This installs a callback that sends the events to the channel, so it
+    /// replaces the callback set before, and a later callback replaces the
+    /// channel. The channel is unbounded and receives the events in the order
+    /// they were dispatched. Once the receiver is dropped, the events are
+    /// silently discarded.
+    pub fn event_channel(&self) -> mpsc::Receiver<Event> {
+        let (sender, receiver) = mpsc::channel();
+        self.inner.set_callback(Box::new(move |event| {
+            // The receiver may be gone, in which case nobody wants the event.
+            _ = sender.send(event);
+        }));
+
+        receiver
+    }
+
+    /// Get a stream of every event for Tokio, the async counterpart of
+    /// [`DisplayObserver::event_channel`].
+    ///
+    /// This installs a callback that sends the events to the stream, so it
+    /// replaces the callback set before. The stream ends once the observer is
+    /// dropped or another callback is set. The events are still dispatched by
+    /// [`DisplayObserver::run`], so it must run on its own thread (the main
+    /// thread on macOS) while the stream is awaited in the runtime:
+    ///
+    /// ```no_run
+    /// use tokio_stream::StreamExt;
+    ///
+    /// fn main() -> Result<(), display_config::Error> {
+    ///     let observer = display_config::DisplayObserver::new()?;
+    ///     let mut stream = observer.stream();
+    ///
+    ///     std::thread::spawn(move || {
+    ///         let runtime = tokio::runtime::Builder::new_current_thread()
+    ///             .build()
+    ///             .unwrap();
+    ///
+    ///         runtime.block_on(async {
+    ///             while let Some(event) = stream.next().await {
+    ///                 println!("{event:?}");
+    ///             }
+    ///         });
+    ///     });
+    ///
+    ///     observer.run()
+    /// }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub fn stream(&self) -> impl tokio_stream::Stream<Item = Event> + Send + Unpin + 'static {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        self.inner.set_callback(Box::new(move |event| {
+            // The stream may be gone, in which case nobody wants the event.
+            _ = sender.send(event);
+        }));
+
+        tokio_stream::wrappers::UnboundedReceiverStream::new(receiver)
+    }
+
+    /// Sets the callback function with every event numbered in the order the
+    /// system reported it.
+    ///
+    /// This is [`DisplayObserver::set_callback`] for consumers that hand events
+    /// over to channels or tasks that may reorder them, and replaces the callback
+    /// set before. The numbers are assigned while the observer is locked, so
+    /// sorting by [`SequencedEvent::seq`] recovers the original order. They start
+    /// at `1` for each callback set with this method.
+    pub fn set_sequenced_callback<F>(&self, mut callback: F)
+    where
+        F: FnMut(SequencedEvent) + Send + 'static,
+    {
+        let mut seq = 0;
+        self.inner.set_callback(Box::new(move |event| {
+            seq += 1;
+            callback(SequencedEvent { seq, event });
+        }));
+    }
+
+    /// Removes the currently set callback function. After calling this, no display events will be dispatched.
+    ///
+    /// Returns `true` if a callback was set, or `false` if there was nothing to remove.
+    pub fn remove_callback(&self) -> bool {
+        self.inner.remove_callback()
+    }
+
+    /// Sets a function to be invoked once whenever the display configuration changes.
+    ///
+    /// This is the simplest way to integrate for apps that just recompute
+    /// everything on any change. It fires at most once per reconfiguration,
+    /// no matter how many [`Event`]s it produced, and works independently of
+    /// [`DisplayObserver::set_callback`].
+    ///
+    /// It follows the delivery of the events: it's only called once at least
+    /// one event of the batch passed the event mapper. Events held back by
+    /// [`DisplayObserver::suspend`] or [`DisplayObserver::set_reconnect_window`]
+    /// call it once they're delivered instead.
+    pub fn on_any_change<F>(&self, callback: F)
+    where
+        F: FnMut() + Send + 'static,
+    {
+        self.inner.on_any_change(Box::new(callback));
+    }
+
+    /// Sets the handler of [`Event::Added`], which receives the added display.
+    ///
+    /// The typed handlers (`on_added`, `on_removed`, ...) receive only the events
+    /// of their kind, after the event mapper. They work alongside
+    /// [`DisplayObserver::set_callback`], and setting one again replaces it.
+    pub fn on_added<F>(&self, mut handler: F)
+    where
+        F: FnMut(Display) + Send + 'static,
+    {
+        self.inner.set_event_handler(
+            EventKind::Added,
+            Box::new(move |event| {
+                if let Event::Added { display, .. } = event {
+                    handler(display);
+                }
+            }),
+        );
+    }
+
+    /// Sets the handler of [`Event::Removed`], which receives the ID of the removed display.
+    pub fn on_removed<F>(&self, mut handler: F)
+    where
+        F: FnMut(DisplayId) + Send + 'static,
+    {
+        self.inner.set_event_handler(
+            EventKind::Removed,
+            Box::new(move |event| {
+                if let Event::Removed { id, .. } = event {
+                    handler(id);
+                }
+            }),
+        );
+    }
+
+    /// Sets the handler of [`Event::SizeChanged`], which receives the display
+    /// and its size before and after the change.
+    pub fn on_size_changed<F>(&self, mut handler: F)
+    where
+        F: FnMut(Display, LogicalSize<u32>, LogicalSize<u32>) + Send + 'static,
+    {
+        self.inner.set_event_handler(
+            EventKind::SizeChanged,
+            Box::new(move |event| {
+                if let Event::SizeChanged {
+                    display,
+                    before,
+                    after,
+                } = event
+                {
+                    handler(display, before, after);
+                }
+            }),
+        );
+    }
+
+    /// Sets the handler of [`Event::OriginChanged`], which receives the display
+    /// and its origin before and after the change.
+    ///
+    /// Use [`DisplayObserver::set_callback`] if you need to know whether the
+    /// change was caused by the primary display moving.
+    pub fn on_origin_changed<F>(&self, mut handler: F)
+    where
+        F: FnMut(Display, LogicalPosition<i32>, LogicalPosition<i32>) + Send + 'static,
+    {
+        self.inner.set_event_handler(
+            EventKind::OriginChanged,
+            Box::new(move |event| {
+                if let Event::OriginChanged {
+                    display,
+                    before,
+                    after,
+                    ..
+                } = event
+                {
+                    handler(display, before, after);
+                }
+            }),
+        );
+    }
+
+    /// Sets the handler of [`Event::Mirrored`], which receives the mirrored display.
+    pub fn on_mirrored<F>(&self, mut handler: F)
+    where
+        F: FnMut(Display) + Send + 'static,
+    {
+        self.inner.set_event_handler(
+            EventKind::Mirrored,
+            Box::new(move |event| {
+                if let Event::Mirrored(display) = event {
+                    handler(display);
+                }
+            }),
+        );
+    }
+
+    /// Sets the handler of [`Event::UnMirrored`], which receives the unmirrored display.
+    pub fn on_unmirrored<F>(&self, mut handler: F)
+    where
+        F: FnMut(Display) + Send + 'static,
+    {
+        self.inner.set_event_handler(
+            EventKind::UnMirrored,
+            Box::new(move |event| {
+                if let Event::UnMirrored(display) = event {
+                    handler(display);
+                }
+            }),
+        );
+    }
+
+    /// Sets the handler of [`Event::ColorProfileChanged`], which receives the display.
+    pub fn on_color_profile_changed<F>(&self, mut handler: F)
+    where
+        F: FnMut(Display) + Send + 'static,
+    {
+        self.inner.set_event_handler(
+            EventKind::ColorProfileChanged,
+            Box::new(move |event| {
+                if let Event::ColorProfileChanged { display } = event {
+                    handler(display);
+                }
+            }),
+        );
+    }
+
+    /// Sets the handler of [`Event::Reconnected`], which receives the display.
+    ///
+    /// This only receives events once enabled with [`DisplayObserver::set_reconnect_window`].
+    pub fn on_reconnected<F>(&self, mut handler: F)
+    where
+        F: FnMut(Display) + Send + 'static,
+    {
+        self.inner.set_event_handler(
+            EventKind::Reconnected,
+            Box::new(move |event| {
+                if let Event::Reconnected { display } = event {
+                    handler(display);
+                }
+            }),
+        );
+    }
+
+    /// Sets the handler of [`Event::MirrorSourceChanged`], which receives the
+    /// display and its mirror source before and after the change.
+    pub fn on_mirror_source_changed<F>(&self, mut handler: F)
+    where
+        F: FnMut(Display, Option<DisplayId>, Option<DisplayId>) + Send + 'static,
+    {
+        self.inner.set_event_handler(
+            EventKind::MirrorSourceChanged,
+            Box::new(move |event| {
+                if let Event::MirrorSourceChanged {
+                    display,
+                    before,
+                    after,
+                } = event
+                {
+                    handler(display, before, after);
+                }
+            }),
+        );
+    }
+
+    /// Sets the handler of [`Event::VirtualOriginChanged`], which receives the
+    /// top-left corner of the bounding box of all displays before and after the change.
+    pub fn on_virtual_origin_changed<F>(&self, mut handler: F)
+    where
+        F: FnMut(LogicalPosition<i32>, LogicalPosition<i32>) + Send + 'static,
+    {
+        self.inner.set_event_handler(
+            EventKind::VirtualOriginChanged,
+            Box::new(move |event| {
+                if let Event::VirtualOriginChanged { before, after } = event {
+                    handler(before, after);
+                }
+            }),
+        );
+    }
+
+    /// Sets the handler of [`Event::PrimaryChanged`], which receives the display
+    /// and whether it was the primary display before the change.
+    pub fn on_primary_changed<F>(&self, mut handler: F)
+    where
+        F: FnMut(Display, bool) + Send + 'static,
+    {
+        self.inner.set_event_handler(
+            EventKind::PrimaryChanged,
+            Box::new(move |event| {
+                if let Event::PrimaryChanged {
+                    display,
+                    was_primary,
+                } = event
+                {
+                    handler(display, was_primary);
+                }
+            }),
+        );
+    }
+
+    /// Sets the handler of [`Event::RefreshRateChanged`], which receives the
+    /// display and its refresh rate before and after the change.
+    pub fn on_refresh_rate_changed<F>(&self, mut handler: F)
+    where
+        F: FnMut(Display, Option<f64>, Option<f64>) + Send + 'static,
+    {
+        self.inner.set_event_handler(
+            EventKind::RefreshRateChanged,
+            Box::new(move |event| {
+                if let Event::RefreshRateChanged {
+                    display,
+                    before,
+                    after,
+                } = event
+                {
+                    handler(display, before, after);
+                }
+            }),
+        );
+    }
+
+    /// Sets the handler of [`Event::RotationChanged`], which receives the
+    /// display and its rotation before and after the change.
+    pub fn on_rotation_changed<F>(&self, mut handler: F)
+    where
+        F: FnMut(Display, DisplayRotation, DisplayRotation) + Send + 'static,
+    {
+        self.inner.set_event_handler(
+            EventKind::RotationChanged,
+            Box::new(move |event| {
+                if let Event::RotationChanged {
+                    display,
+                    before,
+                    after,
+                } = event
+                {
+                    handler(display, before, after);
+                }
+            }),
+        );
+    }
+
+    /// Sets the handler of [`Event::AllDisplaysRemoved`].
+    pub fn on_all_displays_removed<F>(&self, mut handler: F)
+    where
+        F: FnMut() + Send + 'static,
+    {
+        self.inner
+            .set_event_handler(EventKind::AllDisplaysRemoved, Box::new(move |_| handler()));
+    }
+
+    /// Sets the handler of [`Event::DisplaysRestored`].
+    pub fn on_displays_restored<F>(&self, mut handler: F)
+    where
+        F: FnMut() + Send + 'static,
+    {
+        self.inner
+            .set_event_handler(EventKind::DisplaysRestored, Box::new(move |_| handler()));
+    }
+
+    /// Sets a function that runs on every event before the callback.
+    ///
+    /// This can be used to annotate or rewrite events, like middleware.
+    /// If the mapper returns `None`, the event is suppressed and the callback
+    /// is not invoked for it.
+    pub fn set_event_mapper<F>(&self, mapper: F)
+    where
+        F: FnMut(Event) -> Option<Event> + Send + 'static,
+    {
+        self.inner.set_event_mapper(Box::new(mapper));
+    }
+
+    /// Removes the currently set event mapper.
+    ///
+    /// Returns `true` if an event mapper was set, or `false` if there was nothing to remove.
+    pub fn remove_event_mapper(&self) -> bool {
+        self.inner.remove_event_mapper()
+    }
+
+    /// Coalesces a display being removed and added back within `window` into
+    /// a single [`Event::Reconnected`].
+    ///
+    /// This is meant for KVM switches and docks that briefly drop a display,
+    /// so that per-display state isn't torn down and rebuilt. Each
+    /// [`Event::Removed`] is held back for `window`, and only dispatched if the
+    /// display doesn't come back in time. Pass `None` to disable this again,
+    /// which dispatches the removals held back so far on the calling thread.
+    ///
+    /// The removals are otherwise delivered by the event loop, so it must be running.
+    pub fn set_reconnect_window(&self, window: Option<Duration>) {
+        self.inner.set_reconnect_window(window);
+    }
+
+    /// Suspends the dispatch of events, e.g. during a full-screen transition of
+    /// the app itself, until [`DisplayObserver::resume`] is called.
+    ///
+    /// Unlike removing the callback, no change is lost: the observer keeps
+    /// tracking the displays and holds back the events. The function set with
+    /// [`DisplayObserver::on_any_change`] is held back too, and called once on
+    /// resume if the net change isn't empty.
+    pub fn suspend(&self) {
+        self.inner.suspend();
+    }
+
+    /// Resumes the dispatch of events suspended by [`DisplayObserver::suspend`].
+    ///
+    /// The events held back are reduced to their net change and dispatched
+    /// before this returns, so the callback runs on the calling thread. For
+    /// example, a display resized back and forth produces no event, and a display
+    /// added and then moved produces a single [`Event::Added`] with its final state.
+    pub fn resume(&self) {
+        self.inner.resume();
+    }
+
+    /// Block until exactly `count` displays are connected and the configuration
+    /// has not changed for `quiet`, then return the displays.
+    ///
+    /// This is meant for provisioning and test rigs that physically switch
+    /// monitor configurations. A change is seen by the
+    /// [generation][DisplayObserver::generation] of the observer, so even one
+    /// undone right away restarts the quiet period, as long as the event loop
+    /// runs on another thread. The configuration is also polled, so this works
+    /// without the event loop too, though it may then miss a change undone
+    /// between two polls. A failure to get the displays, as may happen in the
+    /// middle of a switch, restarts the quiet period instead of ending the wait.
+    ///
+    /// # Errors
+    /// Returns [`Error::Timeout`] if the configuration is not stable with
+    /// `count` displays within `timeout`, or the error of the last attempt if
+    /// the displays couldn't be got by then. If `timeout` is `None`, this waits
+    /// forever.
+    pub fn wait_for_stable(
+        &self,
+        count: usize,
+        quiet: Duration,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Display>, Error> {
+        const POLL_INTERVAL: Duration = Duration::from_millis(100);
+        const MIN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+        let get_state = || {
+            let generation = self.generation();
+            let displays = get_displays_in(self.inner.coordinate_space()).map(|mut displays| {
+                displays.sort_by(|a, b| a.id.cmp(&b.id));
+                displays
+            });
+            (generation, displays)
+        };
+
+        let started_at = Instant::now();
+        let mut last_changed_at = started_at;
+        let (mut generation, mut displays) = get_state();
+
+        loop {
+            let now = Instant::now();
+
+            if let Ok(current) = &displays
+                && current.len() == count
+                && now - last_changed_at >= quiet
+            {
+                return displays;
+            }
+
+            if let Some(timeout) = timeout
+                && now - started_at >= timeout
+            {
+                return Err(displays.err().unwrap_or(Error::Timeout));
+            }
+
+            std::thread::sleep(POLL_INTERVAL.min(quiet).max(MIN_POLL_INTERVAL));
+
+            let (current_generation, current) = get_state();
+            if current_generation != generation
+                || !matches!((&current, &displays), (Ok(current), Ok(displays)) if current == displays)
+            {
+                last_changed_at = Instant::now();
+            }
+            generation = current_generation;
+            displays = current;
+        }
+    }
+
+    /// Run the event loop until `n` events have been passed to the callback.
+    ///
+    /// Events beyond the `n`th one of the same reconfiguration are not passed
+    /// to the callback. This is useful for tests that script an exact number of
+    /// display changes. Events suppressed by the event mapper don't count, and
+    /// nothing counts if neither a callback nor a typed handler is set.
+    ///
+    /// The same thread requirements as [`DisplayObserver::run`] apply.
+    pub fn run_n(&self, n: usize) -> Result<(), Error> {
+        if n == 0 {
+            return Ok(());
+        }
+
+        let stop_token = self.stop_token();
+        self.inner
+            .set_event_limit(n, Box::new(move || stop_token.stop()));
+
+        let result = self.run();
+        self.inner.remove_event_limit();
+
+        result
+    }
+
+    /// Create an observer with the default configuration, set the callback and
+    /// run the event loop, all in one call.
+    ///
+    /// The same thread requirements as [`DisplayObserver::run`] apply, so on
+    /// macOS this must be called on the main thread. As the observer isn't
+    /// returned, the loop can't be stopped with a [`StopToken`].
+    ///
+    /// ```no_run
+    /// fn main() -> Result<(), display_config::Error> {
+    ///     display_config::DisplayObserver::run_with(|event| println!("{event:?}"))
+    /// }
+    /// ```
+    pub fn run_with<F>(callback: F) -> Result<(), Error>
+    where
+        F: FnMut(Event) + Send + 'static,
+    {
+        let observer = Self::new()?;
+        observer.set_callback(callback);
+        observer.run()
+    }
+
+    /// Get a token that stops [`DisplayObserver::run`] from any thread.
+    pub fn stop_token(&self) -> StopToken {
+        StopToken(self.inner.stop_token())
+    }
+
+    /// Stop [`DisplayObserver::run`], which then returns `Ok(())`.
+    ///
+    /// This is the same as calling [`StopToken::stop`] on a token of this
+    /// observer, and can be called from any thread. To stop the loop from
+    /// inside the callback, move a [`StopToken`] into it instead.
+    pub fn stop(&self) {
+        self.stop_token().stop();
+    }
+
+    /// Process the pending platform messages once without blocking and get the
+    /// events delivered since the last call, for apps that own the event loop,
+    /// like game engines.
+    ///
+    /// Call this each frame instead of [`DisplayObserver::run`]. Once this was
+    /// called, the events are collected until the next call, including the ones
+    /// produced while the event loop of the app handled the platform messages
+    /// itself. The events are also dispatched to the callback and the handlers
+    /// as usual. The same thread requirements as [`DisplayObserver::run`] apply.
+    ///
+    /// # Platform-specific
+    /// - **Windows**: Dispatches the messages of the hidden window of the
+    ///   observer until none are left, leaving the other messages of the thread
+    ///   to the event loop of the app. A [`StopToken`] doesn't quit the event
+    ///   loop of the app, but makes the next [`DisplayObserver::run`] return
+    ///   right away.
+    /// - **macOS**: Dispatches every event of the [`NSApplication`][NSApplication]
+    ///   until none are left, like `pump_events` of winit. Don't call this from
+    ///   inside the event handler of another event loop, which would nest the
+    ///   event pump.
+    ///
+    /// [NSApplication]: https://developer.apple.com/documentation/appkit/nsapplication
+    pub fn poll(&self) -> Vec<Event> {
+        self.inner.poll()
+    }
+
+    /// Run the event loop.
+    /// Since macOS ui thread must be on main, this function must be called on main thread.
+    /// If you call this on non-main thread, this will panic on macOS.
+    /// On Windows, this must be called on the thread that created the observer,
+    /// otherwise this returns an error.
+    ///
+    /// The loop runs until it's stopped with a [`StopToken`].
+    pub fn run(&self) -> Result<(), Error> {
+        #[cfg(target_os = "windows")]
+        {
+            self.inner.run()?;
+            Ok(())
+        }
+        #[cfg(target_os = "macos")]
+        {
+            self.inner.run();
+            Ok(())
+        }
+    }
+}
+
+/// A handle to stop the event loop of a [`DisplayObserver`] from any thread.
+///
+/// The stop request is delivered to the thread running the loop, so this
+/// respects the thread requirements of each platform.
+///
+/// # Platform-specific
+/// - **Windows**: Posts a message to the hidden window of the observer, which
+///   then quits the message loop.
+/// - **macOS**: Stops the [`NSApplication`][NSApplication] on the main thread.
+///   This stops the whole application event loop, not only the observer.
+///
+/// [NSApplication]: https://developer.apple.com/documentation/appkit/nsapplication
+#[derive(Debug, Clone)]
+pub struct StopToken(PlatformStopToken);
+
+impl StopToken {
+    /// Request the event loop to stop.
+    ///
+    /// This does nothing if the loop has already stopped or the observer was dropped.
+    pub fn stop(&self) {
+        self.0.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(crate) fn display(
+        index: u32,
+        origin: (i32, i32),
+        size: (u32, u32),
+        scale_factor: f64,
+    ) -> Display {
+        #[cfg(target_os = "windows")]
+        let id = PlatformDisplayId::new(format!(r"\\.\DISPLAY{index}").into());
+        #[cfg(target_os = "macos")]
+        let id = index;
+
+        Display {
+            id: id.into(),
+            origin: LogicalPosition::new(origin.0, origin.1),
+            size: LogicalSize::new(size.0, size.1),
+            scale_factor,
+            is_primary: origin == (0, 0),
+            is_mirrored: false,
+            mirror_source: None,
+            is_builtin: false,
+            is_virtual: false,
+            rotation: DisplayRotation::default(),
+            refresh_rate: Some(60.0),
+            vrr: None,
+            subpixel: None,
+            input_capabilities: InputCapabilities::default(),
+        }
+    }
+
+    fn assert_close(actual: LogicalPosition<f64>, expected: (f64, f64)) {
+        const EPSILON: f64 = 1e-9;
+
+        assert!(
+            (actual.x - expected.0).abs() < EPSILON && (actual.y - expected.1).abs() < EPSILON,
+            "{actual:?} != {expected:?}",
+        );
+    }
+
+    #[test]
+    fn translates_a_point_between_displays_of_the_same_scale() {
+        let from = display(1, (0, 0), (1920, 1080), 1.0);
+        let to = display(2, (1920, 0), (1920, 1080), 1.0);
+
+        assert_close(
+            translate_point(LogicalPosition::new(100.0, 50.0), &from, &to),
+            (2020.0, 50.0),
+        );
+    }
+
+    #[test]
+    fn translates_a_point_across_scale_factors() {
+        let standard = display(1, (0, 0), (1920, 1080), 1.0);
+        let one_and_a_half = display(2, (1920, 0), (1280, 720), 1.5);
+        let retina = display(3, (0, 1080), (1440, 900), 2.0);
+        let point = LogicalPosition::new(100.0, 50.0);
+
+        // From 2x to 1x, the offset doubles.
+        assert_close(translate_point(point, &retina, &standard), (200.0, -2060.0));
+        // From 1x to 2x, it halves.
+        assert_close(translate_point(point, &standard, &retina), (50.0, 1105.0));
+        // From 1.5x to 1x and back.
+        assert_close(
+            translate_point(
+                LogicalPosition::new(2020.0, 50.0),
+                &one_and_a_half,
+                &standard,
+            ),
+            (150.0, 75.0),
+        );
+        assert_close(
+            translate_point(
+                LogicalPosition::new(150.0, 75.0),
+                &standard,
+                &one_and_a_half,
+            ),
+            (2020.0, 50.0),
+        );
+    }
+
+    #[test]
+    fn translates_a_point_on_negative_origins() {
+        let left = display(1, (-1920, -1080), (1920, 1080), 1.0);
+        let primary = display(2, (0, 0), (1440, 900), 2.0);
+
+        assert_close(
+            translate_point(LogicalPosition::new(-1820.0, -1030.0), &left, &primary),
+            (50.0, 25.0),
+        );
+        assert_close(
+            translate_point(LogicalPosition::new(50.0, 25.0), &primary, &left),
+            (-1820.0, -1030.0),
+        );
+    }
+
+    /// Map the corners of a display through its transform, in the order
+    /// top-left, top-right, bottom-left and bottom-right.
+    fn transformed_corners(display: &Display) -> [(f64, f64); 4] {
+        let [a, b, c, d, tx, ty] = display.transform_matrix();
+        let (width, height) = (
+            f64::from(display.size.width),
+            f64::from(display.size.height),
+        );
+
+        [(0.0, 0.0), (width, 0.0), (0.0, height), (width, height)]
+            .map(|(x, y)| (a * x + c * y + tx, b * x + d * y + ty))
+    }
+
+    #[test]
+    fn transforms_the_corners_of_a_rotated_display() {
+        // A 2x panel of 3840x2160 physical pixels, in its native landscape orientation.
+        let landscape = display(1, (0, 0), (1920, 1080), 2.0);
+        let portrait = display(1, (0, 0), (1080, 1920), 2.0);
+        let cases = [
+            (
+                DisplayRotation::Deg0,
+                &landscape,
+                [(0.0, 0.0), (3840.0, 0.0), (0.0, 2160.0), (3840.0, 2160.0)],
+            ),
+            (
+                DisplayRotation::Deg90,
+                &portrait,
+                [(3840.0, 0.0), (3840.0, 2160.0), (0.0, 0.0), (0.0, 2160.0)],
+            ),
+            (
+                DisplayRotation::Deg180,
+                &landscape,
+                [(3840.0, 2160.0), (0.0, 2160.0), (3840.0, 0.0), (0.0, 0.0)],
+            ),
+            (
+                DisplayRotation::Deg270,
+                &portrait,
+                [(0.0, 2160.0), (0.0, 0.0), (3840.0, 2160.0), (3840.0, 0.0)],
+            ),
+        ];
+
+        for (rotation, display, corners) in cases {
+            let display = Display {
+                rotation,
+                ..display.clone()
+            };
+            assert_eq!(transformed_corners(&display), corners, "{rotation:?}");
+        }
+    }
+
+    #[test]
+    fn finds_the_neighbor_on_each_side() {
+        // [left][primary][right]
+        //       [below]
+        let primary = display(1, (0, 0), (1920, 1080), 1.0);
+        let left = display(2, (-1280, 100), (1280, 1024), 1.0);
+        let right = display(3, (1920, -200), (1920, 1080), 1.0);
+        let below = display(4, (200, 1080), (1440, 900), 1.0);
+        let all = [primary.clone(), left.clone(), right.clone(), below.clone()];
+
+        assert_eq!(
+            primary.neighbor(Direction::Left, &all),
+            Some(left.id.clone())
+        );
+        assert_eq!(
+            primary.neighbor(Direction::Right, &all),
+            Some(right.id.clone())
+        );
+        assert_eq!(primary.neighbor(Direction::Down, &all), Some(below.id));
+        assert_eq!(primary.neighbor(Direction::Up, &all), None);
+        assert_eq!(
+            left.neighbor(Direction::Right, &all),
+            Some(primary.id.clone())
+        );
+        assert_eq!(right.neighbor(Direction::Left, &all), Some(primary.id));
+    }
+
+    #[test]
+    fn finds_a_neighbor_across_a_gap() {
+        let primary = display(1, (0, 0), (1920, 1080), 1.0);
+        let near = display(2, (2000, 0), (1920, 1080), 1.0);
+        let far = display(3, (4000, 0), (1920, 1080), 1.0);
+        let all = [primary.clone(), near.clone(), far.clone()];
+
+        assert_eq!(
+            primary.neighbor(Direction::Right, &all),
+            Some(near.id.clone())
+        );
+        assert_eq!(near.neighbor(Direction::Right, &all), Some(far.id));
+        assert_eq!(near.neighbor(Direction::Left, &all), Some(primary.id));
+    }
+
+    #[test]
+    fn prefers_the_neighbor_sharing_the_longest_edge() {
+        let primary = display(1, (0, 0), (1920, 1080), 1.0);
+        let upper = display(2, (1920, -800), (1920, 1080), 1.0);
+        let lower = display(3, (1920, 280), (1280, 1024), 1.0);
+        let all = [primary.clone(), upper, lower.clone()];
+
+        assert_eq!(primary.neighbor(Direction::Right, &all), Some(lower.id));
+    }
+
+    #[test]
+    fn ignores_diagonal_displays() {
+        let primary = display(1, (0, 0), (1920, 1080), 1.0);
+        let diagonal = display(2, (1920, 1080), (1920, 1080), 1.0);
+        let all = [primary.clone(), diagonal.clone()];
+
+        for direction in [
+            Direction::Left,
+            Direction::Right,
+            Direction::Up,
+            Direction::Down,
+        ] {
+            assert_eq!(primary.neighbor(direction, &all), None, "{direction:?}");
+            assert_eq!(diagonal.neighbor(direction, &all), None, "{direction:?}");
+        }
+    }
+
+    #[test]
+    fn has_no_neighbor_alone() {
+        let primary = display(1, (0, 0), (1920, 1080), 1.0);
+
+        for direction in [
+            Direction::Left,
+            Direction::Right,
+            Direction::Up,
+            Direction::Down,
+        ] {
+            assert_eq!(
+                primary.neighbor(direction, std::slice::from_ref(&primary)),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn has_one_main_display() {
+        let displays = get_displays().unwrap();
+
+        // A headless machine has no main display.
+        if !displays.is_empty() {
+            let main_displays = displays.iter().filter(|display| display.is_main()).count();
+            assert_eq!(main_displays, 1);
         }
     }
 }
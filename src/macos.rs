@@ -1,44 +1,442 @@
 use std::{
     collections::HashMap,
-    ffi::c_void,
-    sync::{Arc, Mutex},
+    ffi::{CStr, CString, c_char, c_int, c_void},
+    ptr::NonNull,
+    sync::{
+        Arc, Mutex, OnceLock, Weak,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
 };
 
+use block2::RcBlock;
+use dispatch2::{DispatchQueue, DispatchTime};
 use dpi::{LogicalPosition, LogicalSize};
+use objc2::{
+    MainThreadMarker,
+    rc::Retained,
+    runtime::{NSObjectProtocol, ProtocolObject},
+};
+use objc2_app_kit::{
+    NSApplication, NSEvent, NSEventMask, NSEventModifierFlags, NSEventType, NSScreen,
+    NSScreenColorSpaceDidChangeNotification,
+};
+use objc2_core_foundation::{
+    CFArray, CFData, CFDictionary, CFNumber, CFPreferencesCopyAppValue, CFRetained, CFRunLoop,
+    CFString, CFType, CFUUID, CGPoint, ConcreteType,
+};
 use objc2_core_graphics::{
-    CGDirectDisplayID, CGDisplayBounds, CGDisplayChangeSummaryFlags, CGDisplayCopyDisplayMode,
-    CGDisplayIsMain, CGDisplayMirrorsDisplay, CGDisplayMode,
-    CGDisplayRegisterReconfigurationCallback, CGDisplayRemoveReconfigurationCallback, CGError,
-    CGGetActiveDisplayList, kCGNullDirectDisplay,
+    CGColorSpace, CGDirectDisplayCopyCurrentMetalDevice, CGDirectDisplayID, CGDisplayBounds,
+    CGDisplayChangeSummaryFlags, CGDisplayCopyAllDisplayModes, CGDisplayCopyColorSpace,
+    CGDisplayCopyDisplayMode, CGDisplayIsBuiltin, CGDisplayMirrorsDisplay, CGDisplayMode,
+    CGDisplayModelNumber, CGDisplayRegisterReconfigurationCallback,
+    CGDisplayRemoveReconfigurationCallback, CGDisplayRotation, CGDisplayScreenSize,
+    CGDisplaySerialNumber, CGDisplayVendorNumber, CGError, CGGetActiveDisplayList, CGMainDisplayID,
+    kCGNullDirectDisplay,
 };
-use smallvec::SmallVec;
+use objc2_foundation::{
+    NSDate, NSDefaultRunLoopMode, NSNotification, NSNotificationCenter, NSNumber, NSString,
+};
+use objc2_metal::MTLDevice;
 
-use crate::{Display, DisplayEventCallback, Event};
+#[cfg(feature = "async")]
+use crate::dispatch::NextEvent;
+use crate::{
+    ChangeReason, CoordinateSpace, Display, DisplayChangeCallback, DisplayEventCallback,
+    DisplayEventMapper, DisplayMode, DisplayRotation, Event, EventBatch, InputCapabilities, Rect,
+    RefreshRate,
+    dispatch::{EventDispatcher, EventKind, lock_state},
+    edid::Edid,
+    sort_modes,
+    tracking::{
+        sort_events, sorted_displays, tag_primary_shift, track_headless_transition,
+        track_virtual_origin,
+    },
+};
 
 /// The type alias for macOS display ID, which is [`CGDirectDisplayID`][CGDirectDisplayID].
 ///
 /// [CGDirectDisplayID]: https://developer.apple.com/documentation/coregraphics/cgdirectdisplayid?language=objc
 pub type MacOSDisplayId = CGDirectDisplayID;
 
-/// The error type for macOS-specific operations, which is [`CGError`][CGError].
-///
-/// [CGError]: https://developer.apple.com/documentation/coregraphics/cgerror?language=objc
-pub type MacOSError = CGError;
+/// The error type for macOS-specific operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum MacOSError {
+    /// A Core Graphics call failed with the given [`CGError`][CGError].
+    ///
+    /// [CGError]: https://developer.apple.com/documentation/coregraphics/cgerror?language=objc
+    #[error("{operation} failed with {error:?}.")]
+    CoreGraphics {
+        /// The function or operation that failed.
+        operation: &'static str,
+        error: CGError,
+    },
+    /// An IOKit call failed with the given `kIOReturn` code.
+    #[error("{operation} failed with kIOReturn {code:#x}.")]
+    IOKit {
+        /// The function or operation that failed.
+        operation: &'static str,
+        code: i32,
+    },
+    /// The display doesn't support the operation.
+    #[error("{operation} is not supported by the display.")]
+    Unsupported {
+        /// The function or operation that failed.
+        operation: &'static str,
+    },
+}
+
+impl MacOSError {
+    /// Get the function or operation that failed.
+    pub fn operation(&self) -> &'static str {
+        match self {
+            Self::CoreGraphics { operation, .. }
+            | Self::IOKit { operation, .. }
+            | Self::Unsupported { operation } => operation,
+        }
+    }
+
+    /// Get the [`CGError`] of a failed Core Graphics call.
+    pub fn cg_error(&self) -> Option<CGError> {
+        match self {
+            Self::CoreGraphics { error, .. } => Some(*error),
+            _ => None,
+        }
+    }
+}
+
+impl From<CGError> for MacOSError {
+    fn from(error: CGError) -> Self {
+        Self::CoreGraphics {
+            operation: "Core Graphics",
+            error,
+        }
+    }
+}
+
+/// The backing scale factors used by macOS.
+pub(crate) const STANDARD_SCALE_FACTORS: &[f64] = &[1.0, 2.0];
 
 trait CGErrorToResult {
-    fn into_result<T>(self, value: T) -> Result<T, MacOSError>;
+    fn into_result<T>(self, operation: &'static str, value: T) -> Result<T, MacOSError>;
 }
 
 impl CGErrorToResult for CGError {
-    fn into_result<T>(self, value: T) -> Result<T, MacOSError> {
+    fn into_result<T>(self, operation: &'static str, value: T) -> Result<T, MacOSError> {
         if self == CGError::Success {
             Ok(value)
         } else {
-            Err(self)
+            Err(MacOSError::CoreGraphics {
+                operation,
+                error: self,
+            })
         }
     }
 }
 
+#[link(name = "CoreDisplay", kind = "framework")]
+unsafe extern "C" {
+    fn CoreDisplay_DisplayCreateInfoDictionary(display: CGDirectDisplayID) -> *mut CFDictionary;
+}
+
+#[link(name = "ColorSync", kind = "framework")]
+unsafe extern "C" {
+    fn CGDisplayCreateUUIDFromDisplayID(display: CGDirectDisplayID) -> *mut CFUUID;
+}
+
+#[link(name = "ApplicationServices", kind = "framework")]
+unsafe extern "C" {
+    fn UAZoomEnabled() -> u8;
+}
+
+/// Get the scale applied by the accessibility zoom.
+///
+/// The zoom factor isn't exposed by the public API, so it's read from the
+/// preferences of the accessibility settings, which a sandboxed app can't read.
+pub(crate) fn get_accessibility_scale() -> Option<f64> {
+    if unsafe { UAZoomEnabled() } == 0 {
+        return Some(1.0);
+    }
+
+    let key = CFString::from_static_str("closeViewZoomFactor");
+    let domain = CFString::from_static_str("com.apple.universalaccess");
+    let factor = CFPreferencesCopyAppValue(&key, &domain)?;
+
+    factor
+        .downcast_ref::<CFNumber>()?
+        .as_f64()
+        .filter(|factor| *factor >= 1.0)
+}
+
+/// The private DisplayServices framework, which controls the brightness of the
+/// built-in display and Apple displays.
+///
+/// Being private, it can't be linked against, so it's loaded on first use.
+struct DisplayServices {
+    can_change_brightness: unsafe extern "C" fn(CGDirectDisplayID) -> bool,
+    get_brightness: unsafe extern "C" fn(CGDirectDisplayID, *mut f32) -> c_int,
+    set_brightness: unsafe extern "C" fn(CGDirectDisplayID, f32) -> c_int,
+}
+
+unsafe extern "C" {
+    fn dlopen(path: *const c_char, mode: c_int) -> *mut c_void;
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+}
+
+const RTLD_LAZY: c_int = 0x1;
+
+impl DisplayServices {
+    const PATH: &CStr =
+        c"/System/Library/PrivateFrameworks/DisplayServices.framework/DisplayServices";
+
+    /// Get the framework, or `None` if it or one of its functions is missing.
+    fn get() -> Option<&'static Self> {
+        static DISPLAY_SERVICES: OnceLock<Option<DisplayServices>> = OnceLock::new();
+
+        DISPLAY_SERVICES
+            .get_or_init(|| unsafe {
+                let handle = NonNull::new(dlopen(Self::PATH.as_ptr(), RTLD_LAZY))?;
+                let symbol = |name: &CStr| NonNull::new(dlsym(handle.as_ptr(), name.as_ptr()));
+
+                // SAFETY: The signatures match the ones of the framework.
+                Some(Self {
+                    can_change_brightness: std::mem::transmute::<
+                        NonNull<c_void>,
+                        unsafe extern "C" fn(CGDirectDisplayID) -> bool,
+                    >(symbol(
+                        c"DisplayServicesCanChangeBrightness",
+                    )?),
+                    get_brightness: std::mem::transmute::<
+                        NonNull<c_void>,
+                        unsafe extern "C" fn(CGDirectDisplayID, *mut f32) -> c_int,
+                    >(symbol(c"DisplayServicesGetBrightness")?),
+                    set_brightness: std::mem::transmute::<
+                        NonNull<c_void>,
+                        unsafe extern "C" fn(CGDirectDisplayID, f32) -> c_int,
+                    >(symbol(c"DisplayServicesSetBrightness")?),
+                })
+            })
+            .as_ref()
+    }
+
+    /// Get the framework if it can change the brightness of the display.
+    fn for_display(id: CGDirectDisplayID) -> Option<&'static Self> {
+        Self::get().filter(|services| unsafe { (services.can_change_brightness)(id) })
+    }
+}
+
+/// Get the brightness of the display, from `0.0` to `1.0`.
+///
+/// Returns `None` if the display doesn't support software brightness control,
+/// which is the case for most external monitors.
+pub fn get_brightness(id: MacOSDisplayId) -> Option<f32> {
+    let services = DisplayServices::for_display(id)?;
+
+    let mut brightness = 0.0;
+    let status = unsafe { (services.get_brightness)(id, &mut brightness) };
+    (status == 0).then_some(brightness.clamp(0.0, 1.0))
+}
+
+/// Set the brightness of the display, from `0.0` to `1.0`.
+///
+/// The brightness is clamped to that range.
+///
+/// # Errors
+/// Returns [`MacOSError::Unsupported`] if the display doesn't support software
+/// brightness control, which is the case for most external monitors.
+pub fn set_brightness(id: MacOSDisplayId, brightness: f32) -> Result<(), MacOSError> {
+    const OPERATION: &str = "DisplayServicesSetBrightness";
+
+    if brightness.is_nan() {
+        return Err(MacOSError::CoreGraphics {
+            operation: OPERATION,
+            error: CGError::IllegalArgument,
+        });
+    }
+
+    let services = DisplayServices::for_display(id).ok_or(MacOSError::Unsupported {
+        operation: OPERATION,
+    })?;
+    let status = unsafe { (services.set_brightness)(id, brightness.clamp(0.0, 1.0)) };
+    if status == 0 {
+        Ok(())
+    } else {
+        Err(MacOSError::IOKit {
+            operation: OPERATION,
+            code: status,
+        })
+    }
+}
+
+/// Get the information dictionary Core Display keeps about the display,
+/// which contains the properties read from the monitor such as its EDID.
+fn get_display_info(id: CGDirectDisplayID) -> Option<CFRetained<CFDictionary>> {
+    let info = unsafe { CoreDisplay_DisplayCreateInfoDictionary(id) };
+    // SAFETY: The dictionary is returned with +1 retain count ("Create" rule).
+    NonNull::new(info).map(|info| unsafe { CFRetained::from_raw(info) })
+}
+
+/// Get the value of the given key in the information dictionary of the display.
+fn get_display_info_value<T: ConcreteType>(
+    info: &CFDictionary,
+    key: &'static str,
+) -> Option<CFRetained<T>> {
+    let key = CFString::from_static_str(key);
+    let value = unsafe { info.value(&*key as *const CFString as *const c_void) };
+    // SAFETY: The value is owned by the dictionary, which outlives this borrow.
+    let value = unsafe { (value as *const CFType).as_ref() }?;
+
+    value.downcast_ref::<T>().map(|value| value.retain())
+}
+
+/// The key of a monitor in the EDID cache: the display ID it's shown as, and
+/// its vendor, model and serial numbers, since display IDs may be reused.
+type EdidCacheKey = (CGDirectDisplayID, u32, u32, u32);
+
+/// Read the EDID of the display, if Core Display provides it.
+///
+/// Reading it goes through the private Core Display API, which is too slow to
+/// repeat on every enumeration, so the EDIDs read are cached. Failed reads aren't
+/// cached and are retried next time.
+fn read_edid(id: CGDirectDisplayID) -> Option<Vec<u8>> {
+    static CACHE: OnceLock<Mutex<HashMap<EdidCacheKey, Vec<u8>>>> = OnceLock::new();
+
+    let key = (
+        id,
+        CGDisplayVendorNumber(id),
+        CGDisplayModelNumber(id),
+        CGDisplaySerialNumber(id),
+    );
+    let cache = CACHE.get_or_init(Default::default);
+    if let Some(edid) = lock_state(cache).get(&key) {
+        return Some(edid.clone());
+    }
+
+    let info = get_display_info(id)?;
+    let edid = get_display_info_value::<CFData>(&info, "IODisplayEDID")?.to_vec();
+    lock_state(cache).insert(key, edid.clone());
+    Some(edid)
+}
+
+/// Get the ID of the display shown as the given screen.
+fn get_screen_display_id(screen: &NSScreen) -> Option<MacOSDisplayId> {
+    let key = NSString::from_str("NSScreenNumber");
+    let number = screen.deviceDescription().objectForKey(&key)?;
+    number
+        .downcast::<NSNumber>()
+        .ok()
+        .map(|number| number.unsignedIntValue())
+}
+
+/// Get the name of the display shown in the system settings, e.g. `DELL U2720Q`.
+///
+/// `NSScreen` is only available on the main thread, so the product name from
+/// the EDID is used on other threads, which built-in panels don't have.
+pub(crate) fn get_friendly_name(id: MacOSDisplayId) -> Option<String> {
+    let Some(mtm) = MainThreadMarker::new() else {
+        return get_edid(id)?.product_name();
+    };
+
+    NSScreen::screens(mtm)
+        .iter()
+        .find(|screen| get_screen_display_id(screen) == Some(id))
+        .map(|screen| screen.localizedName().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// Whether the display can show HDR content, i.e. has a potential EDR headroom.
+///
+/// This is always `false` off the main thread, where `NSScreen` isn't available.
+pub(crate) fn is_display_hdr_capable(id: MacOSDisplayId) -> bool {
+    let Some(mtm) = MainThreadMarker::new() else {
+        return false;
+    };
+
+    NSScreen::screens(mtm)
+        .iter()
+        .find(|screen| get_screen_display_id(screen) == Some(id))
+        .is_some_and(|screen| {
+            screen.maximumPotentialExtendedDynamicRangeColorComponentValue() > 1.0
+        })
+}
+
+/// Get the display modes supported by the display, without duplicates.
+pub(crate) fn get_available_modes(id: MacOSDisplayId) -> Result<Vec<DisplayMode>, MacOSError> {
+    let modes =
+        unsafe { CGDisplayCopyAllDisplayModes(id, None) }.ok_or(MacOSError::CoreGraphics {
+            operation: "CGDisplayCopyAllDisplayModes",
+            error: CGError::Failure,
+        })?;
+    // SAFETY: The array returned by `CGDisplayCopyAllDisplayModes` holds `CGDisplayMode`s.
+    let modes = unsafe { CFRetained::cast_unchecked::<CFArray<CGDisplayMode>>(modes) };
+
+    let hdr_capable = is_display_hdr_capable(id);
+    let mut modes: Vec<_> = modes
+        .iter()
+        .map(|mode| {
+            let refresh_rate = CGDisplayMode::refresh_rate(Some(&mode));
+            DisplayMode {
+                size: LogicalSize::new(
+                    CGDisplayMode::width(Some(&mode)) as u32,
+                    CGDisplayMode::height(Some(&mode)) as u32,
+                ),
+                // Built-in panels report 0, as their refresh rate isn't fixed.
+                refresh_rate: (refresh_rate > 0.0).then_some(refresh_rate),
+                exact_refresh_rate: RefreshRate::from_hz(refresh_rate),
+                hdr_capable,
+            }
+        })
+        .collect();
+
+    sort_modes(&mut modes);
+    Ok(modes)
+}
+
+/// Get the parsed EDID of the display.
+pub(crate) fn get_edid(id: MacOSDisplayId) -> Option<Edid> {
+    read_edid(id).and_then(Edid::new)
+}
+
+/// Get the physical size of the display as `(width, height)` in millimeters.
+pub(crate) fn get_physical_size_mm(id: MacOSDisplayId) -> Option<(f64, f64)> {
+    let size = CGDisplayScreenSize(id);
+    (size.width > 0.0 && size.height > 0.0)
+        .then_some((size.width, size.height))
+        .or_else(|| get_edid(id)?.physical_size_mm())
+}
+
+/// Get a key of the display that stays the same across reconnections and
+/// reboots: the UUID macOS assigns to the display, or the vendor, model and
+/// serial numbers of the monitor if there's no UUID.
+pub(crate) fn get_persistent_key(id: MacOSDisplayId) -> String {
+    let uuid = unsafe { CGDisplayCreateUUIDFromDisplayID(id) };
+    // SAFETY: The UUID is returned with +1 retain count ("Create" rule).
+    let uuid = NonNull::new(uuid).map(|uuid| unsafe { CFRetained::from_raw(uuid) });
+
+    match uuid.and_then(|uuid| CFUUID::new_string(None, Some(&uuid))) {
+        Some(uuid) => uuid.to_string(),
+        None => format!(
+            "{:08x}-{:08x}-{:08x}",
+            CGDisplayVendorNumber(id),
+            CGDisplayModelNumber(id),
+            CGDisplaySerialNumber(id)
+        ),
+    }
+}
+
+/// The vendor number Core Graphics reports for displays not backed by a monitor,
+/// `'unkn'` as a four-character code.
+const UNKNOWN_VENDOR_NUMBER: u32 = 0x756E_6B6E;
+
+/// Guess whether the display is virtual, e.g. created by screen-sharing or AirPlay.
+///
+/// A physical display has a known vendor. A missing EDID isn't taken as a sign
+/// of a virtual display, since the EDID is read through a private API that may
+/// fail for real monitors too.
+fn is_display_virtual(id: CGDirectDisplayID, is_builtin: bool) -> bool {
+    !is_builtin && CGDisplayVendorNumber(id) == UNKNOWN_VENDOR_NUMBER
+}
+
 fn get_scale_factor(id: CGDirectDisplayID) -> f64 {
     let mode = CGDisplayCopyDisplayMode(id);
     let pixel_width = CGDisplayMode::pixel_width(mode.as_deref());
@@ -47,13 +445,42 @@ fn get_scale_factor(id: CGDirectDisplayID) -> f64 {
     pixel_width as f64 / point_width as f64
 }
 
+/// Get the refresh rate of the current mode of the display in Hz.
+fn get_refresh_rate(id: CGDirectDisplayID) -> Option<f64> {
+    let mode = CGDisplayCopyDisplayMode(id);
+    let refresh_rate = CGDisplayMode::refresh_rate(mode.as_deref());
+
+    // Built-in panels report 0, as their refresh rate isn't fixed.
+    (refresh_rate > 0.0).then_some(refresh_rate)
+}
+
+/// Get the bounds of the main display in points, or `None` if there's no display.
+pub(crate) fn get_primary_bounds() -> Option<Rect> {
+    let bounds = CGDisplayBounds(CGMainDisplayID());
+
+    (bounds.size.width > 0.0 && bounds.size.height > 0.0).then(|| Rect {
+        origin: LogicalPosition::new(bounds.origin.x, bounds.origin.y),
+        size: LogicalSize::new(bounds.size.width, bounds.size.height),
+    })
+}
+
 pub fn get_macos_display(id: MacOSDisplayId) -> Display {
     let bounds = CGDisplayBounds(id);
     let origin = LogicalPosition::new(bounds.origin.x as i32, bounds.origin.y as i32);
     let size = LogicalSize::new(bounds.size.width as u32, bounds.size.height as u32);
-    let is_primary = CGDisplayIsMain(id);
-    let is_mirrored = CGDisplayMirrorsDisplay(id) != kCGNullDirectDisplay;
+    // `CGDisplayIsMain` may report a member of a mirror set as well, so compare
+    // against the one main display to keep `is_primary` unique.
+    let is_primary = id == CGMainDisplayID();
+    let mirror_source = CGDisplayMirrorsDisplay(id);
+    let is_mirrored = mirror_source != kCGNullDirectDisplay;
+    let is_builtin = CGDisplayIsBuiltin(id);
+    let rotation = DisplayRotation::from_degrees(CGDisplayRotation(id));
     let scale_factor = get_scale_factor(id);
+    let refresh_rate = get_refresh_rate(id);
+    let edid = get_edid(id);
+    let is_virtual = is_display_virtual(id, is_builtin);
+    let vrr = edid.as_ref().map(Edid::vrr_info);
+    let subpixel = edid.as_ref().map(Edid::subpixel_layout);
 
     Display {
         id: id.into(),
@@ -62,6 +489,15 @@ pub fn get_macos_display(id: MacOSDisplayId) -> Display {
         scale_factor,
         is_primary,
         is_mirrored,
+        mirror_source: is_mirrored.then(|| mirror_source.into()),
+        is_builtin,
+        is_virtual,
+        rotation,
+        refresh_rate,
+        vrr,
+        subpixel,
+        // Macs have no touch or pen displays, and tablets aren't tied to a display.
+        input_capabilities: InputCapabilities::default(),
     }
 }
 
@@ -91,6 +527,73 @@ impl DisplayMacOSExt for Display {
     }
 }
 
+/// Get the registry ID of the GPU currently driving the display.
+///
+/// Returns `None` if no Metal device is associated with the display.
+pub fn get_gpu_registry_id(id: MacOSDisplayId) -> Option<u64> {
+    CGDirectDisplayCopyCurrentMetalDevice(id).map(|device| device.registryID())
+}
+
+/// Get the IDs of all currently active macOS displays.
+///
+/// The IDs are in the order returned by [`CGGetActiveDisplayList`][CGGetActiveDisplayList],
+/// which puts the main display first. This is cheaper than [`get_macos_displays`]
+/// when only the IDs are needed.
+///
+/// # Errors
+/// This function can return a [`MacOSError`] if there's an issue with Core Graphics.
+///
+/// [CGGetActiveDisplayList]: https://developer.apple.com/documentation/coregraphics/cggetactivedisplaylist(_:_:_:)?language=objc
+pub fn get_active_display_ids() -> Result<Vec<MacOSDisplayId>, MacOSError> {
+    active_display_ids()
+}
+
+/// Get the IDs of the active displays, however many there are.
+///
+/// The count is queried first, so the list is never truncated. A display added
+/// between both calls is left out, and reported by the reconfiguration that
+/// follows.
+fn active_display_ids() -> Result<Vec<MacOSDisplayId>, MacOSError> {
+    list_display_ids(|max_displays, displays, display_count| unsafe {
+        CGGetActiveDisplayList(max_displays, displays, display_count)
+    })
+}
+
+/// Call a function shaped like `CGGetActiveDisplayList` twice, first for the
+/// count and then for a list of that size.
+fn list_display_ids(
+    mut get_display_list: impl FnMut(u32, *mut MacOSDisplayId, &mut u32) -> CGError,
+) -> Result<Vec<MacOSDisplayId>, MacOSError> {
+    let mut display_count = 0;
+    get_display_list(0, std::ptr::null_mut(), &mut display_count)
+        .into_result("CGGetActiveDisplayList", ())?;
+
+    let mut displays = vec![0; display_count as usize];
+    get_display_list(display_count, displays.as_mut_ptr(), &mut display_count)
+        .into_result("CGGetActiveDisplayList", ())?;
+    displays.truncate(display_count as usize);
+
+    Ok(displays)
+}
+
+/// Get the number of displays in the mirror set of the display, including itself.
+///
+/// # Errors
+/// Returns a [`MacOSError`] if the active displays can't be listed.
+pub fn get_mirror_set_size(id: MacOSDisplayId) -> Result<usize, MacOSError> {
+    let source_of = |id| match CGDisplayMirrorsDisplay(id) {
+        source if source == kCGNullDirectDisplay => id,
+        source => source,
+    };
+    let source = source_of(id);
+
+    let size = active_display_ids()?
+        .into_iter()
+        .filter(|other| source_of(*other) == source)
+        .count();
+    Ok(size.max(1))
+}
+
 /// Get a list of all currently active macOS displays.
 ///
 /// # Returns
@@ -99,41 +602,58 @@ impl DisplayMacOSExt for Display {
 /// # Errors
 /// This function can return a [`MacOSError`] if there's an issue with Core Graphics.
 pub fn get_macos_displays() -> Result<Vec<Display>, MacOSError> {
-    const MAX_DISPLAYS: u32 = 20;
-    let mut active_displays = [0; MAX_DISPLAYS as _];
-    let mut display_count = 0;
-
-    unsafe {
-        CGGetActiveDisplayList(
-            MAX_DISPLAYS,
-            &raw mut active_displays as *mut _,
-            &mut display_count,
-        )
-        .into_result(())?;
-    }
-
-    let mut displays = Vec::new();
-    for &display_id in active_displays.iter().take(display_count as usize) {
-        displays.push(get_macos_display(display_id));
-    }
+    Ok(active_display_ids()?
+        .into_iter()
+        .map(get_macos_display)
+        .collect())
+}
 
-    Ok(displays)
+/// Get the ICC data of the color space assigned to the display.
+fn get_color_profile(id: MacOSDisplayId) -> Option<Vec<u8>> {
+    let color_space = CGDisplayCopyColorSpace(id);
+    CGColorSpace::icc_data(Some(&color_space)).map(|data| data.to_vec())
 }
 
 #[derive(Default)]
 struct EventTracker {
     cached_displays: HashMap<MacOSDisplayId, Display>,
+    /// The displays before the last tracked change.
+    previous_displays: HashMap<MacOSDisplayId, Display>,
+    color_profiles: HashMap<MacOSDisplayId, Option<Vec<u8>>>,
+    coordinate_space: CoordinateSpace,
+    /// Whether the last read of the displays failed or looked transitional, so
+    /// the changes should be tracked again a little later.
+    unsettled: bool,
 }
 
 impl EventTracker {
-    fn new() -> Result<Self, MacOSError> {
-        Ok(Self {
-            cached_displays: Self::collect_new_cached_state()?,
-        })
+    fn new(coordinate_space: CoordinateSpace) -> Result<Self, MacOSError> {
+        let cached_displays = Self::collect_new_cached_state(coordinate_space)?;
+        let mut tracker = Self {
+            previous_displays: cached_displays.clone(),
+            cached_displays,
+            color_profiles: HashMap::new(),
+            coordinate_space,
+            unsettled: false,
+        };
+        tracker.track_color_profiles();
+
+        Ok(tracker)
     }
 
-    fn collect_new_cached_state() -> Result<HashMap<MacOSDisplayId, Display>, MacOSError> {
-        let displays = get_macos_displays()?;
+    /// Convert a display read from Core Graphics into the coordinate space of the tracker.
+    fn convert(&self, mut display: Display) -> Display {
+        let primary_scale_factor = get_scale_factor(CGMainDisplayID());
+        self.coordinate_space
+            .convert_display(&mut display, primary_scale_factor);
+        display
+    }
+
+    fn collect_new_cached_state(
+        coordinate_space: CoordinateSpace,
+    ) -> Result<HashMap<MacOSDisplayId, Display>, MacOSError> {
+        let mut displays = get_macos_displays()?;
+        coordinate_space.convert(&mut displays);
         let mut cached_state = HashMap::new();
 
         for display in displays {
@@ -145,21 +665,65 @@ impl EventTracker {
     }
 
     fn add(&mut self, display: Display) {
+        self.previous_displays = self.cached_displays.clone();
         let id = *display.id.macos_id();
         self.cached_displays.insert(id, display);
     }
 
     fn remove(&mut self, id: MacOSDisplayId) {
+        self.previous_displays = self.cached_displays.clone();
         self.cached_displays.remove(&id);
     }
 
-    fn track_changes(&mut self) -> Result<SmallVec<[Event; 4]>, MacOSError> {
-        let before =
-            std::mem::replace(&mut self.cached_displays, Self::collect_new_cached_state()?);
-        let mut events = SmallVec::new();
+    /// Add the events about all displays caused by the last addition or removal:
+    /// [`Event::VirtualOriginChanged`], and [`Event::AllDisplaysRemoved`] or
+    /// [`Event::DisplaysRestored`].
+    fn track_arrangement(&self, events: &mut EventBatch) {
+        track_headless_transition(
+            events,
+            self.previous_displays.len(),
+            self.cached_displays.len(),
+        );
+        track_virtual_origin(
+            events,
+            self.previous_displays.values(),
+            self.cached_displays.values(),
+        );
+    }
+
+    /// Compare the displays with the cached ones.
+    ///
+    /// In the middle of a reconfiguration, Core Graphics may fail or report a
+    /// zero size or an out-of-range origin for an active display. Such a read
+    /// marks the tracker as unsettled, so the caller can track the changes
+    /// again once the reconfiguration is over.
+    fn track_changes(&mut self) -> Result<EventBatch, MacOSError> {
+        self.unsettled = true;
+        let before = std::mem::replace(
+            &mut self.cached_displays,
+            Self::collect_new_cached_state(self.coordinate_space)?,
+        );
+        self.unsettled = false;
+        let mut events = EventBatch::new();
 
         for (id, before_display) in before.iter() {
-            if let Some(after_display) = self.cached_displays.get(id) {
+            if let Some(after_display) = self.cached_displays.get_mut(id) {
+                // Don't report a transitional read, and keep comparing against the
+                // last valid state until the display settles.
+                if !after_display.is_sane() {
+                    *after_display = before_display.clone();
+                    self.unsettled = true;
+                    continue;
+                }
+
+                if before_display.rotation != after_display.rotation {
+                    events.push(Event::RotationChanged {
+                        display: (*after_display).clone(),
+                        before: before_display.rotation,
+                        after: after_display.rotation,
+                    });
+                }
+
                 if before_display.size != after_display.size {
                     events.push(Event::SizeChanged {
                         display: (*after_display).clone(),
@@ -173,17 +737,136 @@ impl EventTracker {
                         display: (*after_display).clone(),
                         before: before_display.origin,
                         after: after_display.origin,
+                        caused_by_primary_shift: false,
+                    });
+                }
+
+                if before_display.refresh_rate != after_display.refresh_rate {
+                    events.push(Event::RefreshRateChanged {
+                        display: (*after_display).clone(),
+                        before: before_display.refresh_rate,
+                        after: after_display.refresh_rate,
+                    });
+                }
+
+                if before_display.is_primary != after_display.is_primary {
+                    events.push(Event::PrimaryChanged {
+                        display: (*after_display).clone(),
+                        was_primary: before_display.is_primary,
+                    });
+                }
+
+                if before_display.is_mirrored
+                    && after_display.is_mirrored
+                    && before_display.mirror_source != after_display.mirror_source
+                {
+                    events.push(Event::MirrorSourceChanged {
+                        display: (*after_display).clone(),
+                        before: before_display.mirror_source.clone(),
+                        after: after_display.mirror_source.clone(),
+                    });
+                }
+            }
+        }
+
+        track_virtual_origin(&mut events, before.values(), self.cached_displays.values());
+        self.previous_displays = before;
+
+        tag_primary_shift(&mut events, self.cached_displays.values());
+        sort_events(&mut events);
+        Ok(events)
+    }
+
+    /// Compare all displays with the cached ones, including the additions,
+    /// removals and mirroring otherwise reported by the callbacks of single displays.
+    fn reconcile(&mut self) -> Result<EventBatch, MacOSError> {
+        let before = self.cached_displays.clone();
+        let mut events = self.track_changes()?;
+
+        for (id, before_display) in &before {
+            match self.cached_displays.get(id) {
+                Some(after_display) if before_display.is_mirrored != after_display.is_mirrored => {
+                    events.push(if after_display.is_mirrored {
+                        Event::Mirrored(after_display.clone())
+                    } else {
+                        Event::UnMirrored(after_display.clone())
                     });
                 }
+                Some(_) => {}
+                None => events.push(Event::Removed {
+                    id: (*id).into(),
+                    reason: ChangeReason::Arrangement,
+                }),
             }
         }
 
+        for (id, after_display) in &self.cached_displays {
+            if !before.contains_key(id) {
+                events.push(Event::Added {
+                    display: after_display.clone(),
+                    reason: ChangeReason::Arrangement,
+                });
+            }
+        }
+
+        track_headless_transition(&mut events, before.len(), self.cached_displays.len());
+        events.extend(self.track_color_profiles());
+        sort_events(&mut events);
         Ok(events)
     }
+
+    /// Compare the color profiles of the displays with the ones seen last time.
+    fn track_color_profiles(&mut self) -> EventBatch {
+        let mut events = EventBatch::new();
+
+        self.color_profiles
+            .retain(|id, _| self.cached_displays.contains_key(id));
+
+        for (id, display) in &self.cached_displays {
+            let profile = get_color_profile(*id);
+
+            // Displays seen for the first time have no previous profile to compare with.
+            if let Some(before) = self.color_profiles.insert(*id, profile.clone())
+                && before != profile
+            {
+                events.push(Event::ColorProfileChanged {
+                    display: display.clone(),
+                });
+            }
+        }
+
+        sort_events(&mut events);
+        events
+    }
+}
+
+unsafe extern "C" {
+    fn syslog(priority: c_int, message: *const c_char, ...);
 }
 
+const LOG_NOTICE: c_int = 5;
+
+/// Write an event to the unified log.
+///
+/// `os_log` is a C macro which can't be called from Rust, so this goes through
+/// `syslog`, which macOS forwards to the unified log.
+pub(crate) fn report_event(event: &Event) {
+    let Ok(message) = CString::new(format!("display-config: {event:?}")) else {
+        return;
+    };
+    unsafe { syslog(LOG_NOTICE, c"%s".as_ptr(), message.as_ptr()) };
+}
+
+/// The token of an observer registered to [`NSNotificationCenter`].
+struct NotificationObserver(Retained<ProtocolObject<dyn NSObjectProtocol>>);
+
+// SAFETY: The token is an opaque object only passed back to `NSNotificationCenter`,
+// which is thread-safe.
+unsafe impl Send for NotificationObserver {}
+unsafe impl Sync for NotificationObserver {}
+
 struct UserInfo {
-    callback: Option<DisplayEventCallback>,
+    dispatcher: EventDispatcher,
     tracker: EventTracker,
 }
 
@@ -194,6 +877,9 @@ struct UserInfo {
 /// like resolution and origin, which are not directly provided by the callback.
 pub struct MacOSDisplayObserver {
     user_info: Arc<Mutex<UserInfo>>,
+    generation: Arc<AtomicU64>,
+    color_space_observer: NotificationObserver,
+    coordinate_space: CoordinateSpace,
 }
 
 impl MacOSDisplayObserver {
@@ -202,31 +888,188 @@ impl MacOSDisplayObserver {
     /// This function sets up the necessary Core Graphics callbacks to begin observing
     /// display configuration changes.
     pub fn new() -> Result<Self, MacOSError> {
+        Self::with_coordinate_space(CoordinateSpace::default())
+    }
+
+    /// Creates a new `MacOSDisplayObserver` reporting the geometry of the
+    /// displays in the given coordinate space.
+    pub fn with_coordinate_space(coordinate_space: CoordinateSpace) -> Result<Self, MacOSError> {
+        let dispatcher = EventDispatcher::default();
+        let generation = dispatcher.generation();
         let user_info = Arc::new(Mutex::new(UserInfo {
-            callback: None,
-            tracker: EventTracker::new()?,
+            dispatcher,
+            tracker: EventTracker::new(coordinate_space)?,
         }));
 
         unsafe {
             let user_info = Arc::as_ptr(&user_info) as *mut c_void;
             CGDisplayRegisterReconfigurationCallback(Some(display_callback), user_info)
-                .into_result(())?;
+                .into_result("CGDisplayRegisterReconfigurationCallback", ())?;
         }
 
-        Ok(Self { user_info })
+        let color_space_observer = {
+            let user_info = Arc::clone(&user_info);
+            let block = RcBlock::new(move |_: NonNull<NSNotification>| {
+                let mut user_info = lock_state(&user_info);
+
+                let events = user_info.tracker.track_color_profiles();
+                user_info.dispatcher.dispatch(events);
+            });
+
+            unsafe {
+                NSNotificationCenter::defaultCenter().addObserverForName_object_queue_usingBlock(
+                    Some(NSScreenColorSpaceDidChangeNotification),
+                    None,
+                    None,
+                    &block,
+                )
+            }
+        };
+
+        Ok(Self {
+            user_info,
+            generation,
+            color_space_observer: NotificationObserver(color_space_observer),
+            coordinate_space,
+        })
     }
 
     /// Sets the callback function to be invoked when a display event occurs.
     pub fn set_callback(&self, callback: DisplayEventCallback) {
-        let mut user_info = self.user_info.lock().unwrap();
-        user_info.callback = Some(callback);
+        let mut user_info = lock_state(&self.user_info);
+        user_info.dispatcher.set_callback(callback);
     }
 
     /// Removes the currently set callback function.
     /// After calling this, no display events will be dispatched.
-    pub fn remove_callback(&self) {
-        let mut user_info = self.user_info.lock().unwrap();
-        user_info.callback = None;
+    ///
+    /// Returns `true` if a callback was set.
+    pub fn remove_callback(&self) -> bool {
+        let mut user_info = lock_state(&self.user_info);
+        user_info.dispatcher.remove_callback()
+    }
+
+    /// Sets the function to be invoked once per display reconfiguration,
+    /// regardless of how many events it produced.
+    ///
+    /// Core Graphics reports a reconfiguration once for every display it
+    /// affects, so the function is called on the main queue once all the
+    /// reports were handled.
+    pub fn on_any_change(&self, callback: DisplayChangeCallback) {
+        let mut user_info = lock_state(&self.user_info);
+        user_info.dispatcher.set_change_callback(callback);
+    }
+
+    /// Sets the function that transforms each event before it reaches the callback.
+    /// If the mapper returns `None`, the event is not dispatched.
+    pub fn set_event_mapper(&self, mapper: DisplayEventMapper) {
+        let mut user_info = lock_state(&self.user_info);
+        user_info.dispatcher.set_mapper(mapper);
+    }
+
+    /// Sets how long a removed display is given to come back before
+    /// [`Event::Removed`] is dispatched, or `None` to dispatch it right away.
+    ///
+    /// If it comes back in time, [`Event::Reconnected`] is dispatched instead of
+    /// both events. The removals held back are delivered on the main dispatch
+    /// queue, so they need the [`NSApplication`][NSApplication] event loop to be running.
+    ///
+    /// [NSApplication]: https://developer.apple.com/documentation/appkit/nsapplication
+    pub fn set_reconnect_window(&self, window: Option<Duration>) {
+        let mut user_info = lock_state(&self.user_info);
+        user_info.dispatcher.set_reconnect_window(window);
+    }
+
+    /// Sets whether every event is also written to the unified log.
+    pub fn set_log_to_os(&self, enabled: bool) {
+        let mut user_info = lock_state(&self.user_info);
+        user_info.dispatcher.set_log_to_os(enabled);
+    }
+
+    /// Holds back the events until [`Self::resume`] is called.
+    pub fn suspend(&self) {
+        let mut user_info = lock_state(&self.user_info);
+        user_info.dispatcher.suspend();
+    }
+
+    /// Dispatches the net change of the events held back since [`Self::suspend`].
+    pub fn resume(&self) {
+        let mut user_info = lock_state(&self.user_info);
+        user_info.dispatcher.resume();
+    }
+
+    /// Gets the coordinate space of the geometry of the displays.
+    pub fn coordinate_space(&self) -> CoordinateSpace {
+        self.coordinate_space
+    }
+
+    /// Gets the number of reconfigurations that produced events so far.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Gets the generation together with the displays known to the observer,
+    /// sorted by ID.
+    ///
+    /// Both are read under the same lock, so the displays are exactly the
+    /// ones of that generation.
+    pub fn snapshot(&self) -> (u64, Vec<Display>) {
+        let user_info = lock_state(&self.user_info);
+        let displays = sorted_displays(user_info.tracker.cached_displays.values());
+
+        (self.generation(), displays)
+    }
+
+    /// Compares the displays with the ones known to the observer right away,
+    /// dispatches the events of the difference and returns them.
+    ///
+    /// The events are dispatched on the calling thread, so the callback and
+    /// the handlers run on it before this returns.
+    pub fn reconcile_blocking(&self) -> Result<Vec<Event>, MacOSError> {
+        let mut user_info = lock_state(&self.user_info);
+        let events = user_info.tracker.reconcile()?;
+
+        user_info.dispatcher.dispatch(events.clone());
+        schedule_removal_flush(Arc::downgrade(&self.user_info), &user_info.dispatcher);
+        Ok(events.into_vec())
+    }
+
+    /// Gets the displays before and after the last tracked change, each sorted by ID.
+    pub fn last_snapshot(&self) -> (Vec<Display>, Vec<Display>) {
+        let user_info = lock_state(&self.user_info);
+        (
+            sorted_displays(user_info.tracker.previous_displays.values()),
+            sorted_displays(user_info.tracker.cached_displays.values()),
+        )
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn next_event(&self) -> NextEvent {
+        let mut user_info = lock_state(&self.user_info);
+        user_info.dispatcher.next_event()
+    }
+
+    pub(crate) fn set_event_handler(&self, kind: EventKind, handler: DisplayEventCallback) {
+        let mut user_info = lock_state(&self.user_info);
+        user_info.dispatcher.set_handler(kind, handler);
+    }
+
+    pub(crate) fn set_event_limit(&self, count: usize, on_reached: Box<dyn FnMut() + Send>) {
+        let mut user_info = lock_state(&self.user_info);
+        user_info.dispatcher.set_limit(count, on_reached);
+    }
+
+    pub(crate) fn remove_event_limit(&self) {
+        let mut user_info = lock_state(&self.user_info);
+        user_info.dispatcher.remove_limit();
+    }
+
+    /// Removes the currently set event mapper.
+    ///
+    /// Returns `true` if an event mapper was set.
+    pub fn remove_event_mapper(&self) -> bool {
+        let mut user_info = lock_state(&self.user_info);
+        user_info.dispatcher.remove_mapper()
     }
 
     /// Runs the [`NSApplication`][NSApplication] event loop to start handling display events.
@@ -238,9 +1081,134 @@ impl MacOSDisplayObserver {
     ///
     /// [NSApplication]: https://developer.apple.com/documentation/appkit/nsapplication
     pub fn run(&self) {
-        let mtm =
-            objc2::MainThreadMarker::new().expect("This function must be called on main thread");
-        objc2_app_kit::NSApplication::sharedApplication(mtm).run();
+        let mtm = MainThreadMarker::new().expect("This function must be called on main thread");
+        NSApplication::sharedApplication(mtm).run();
+    }
+
+    /// Runs the Core Foundation run loop of the main thread to start handling
+    /// display events, without the [`NSApplication`][NSApplication] event loop.
+    ///
+    /// The Core Graphics notifications only need the run loop, so this suits
+    /// command-line tools and daemons, which don't want the side effects of an
+    /// application such as a Dock icon. [`Event::ColorProfileChanged`] relies on
+    /// `NSScreen` notifications, which need the application, so it isn't
+    /// reported this way.
+    ///
+    /// This function will block the current thread and dispatch events.
+    ///
+    /// # Panics
+    /// This function must be called on the main thread, otherwise it will panic.
+    ///
+    /// [NSApplication]: https://developer.apple.com/documentation/appkit/nsapplication
+    pub fn run_cf(&self) {
+        assert!(
+            MainThreadMarker::new().is_some(),
+            "This function must be called on main thread"
+        );
+        CFRunLoop::run();
+    }
+
+    /// Processes the pending events of the application once without blocking
+    /// and returns the display events delivered since the last call.
+    ///
+    /// Once this was called, the display events are collected even while the
+    /// event loop of the app handles the events itself, so none is missed.
+    ///
+    /// This pumps the [`NSApplication`][NSApplication] event queue like
+    /// [`MacOSDisplayObserver::run`] does, so it can be called each frame from
+    /// an event loop of the app's own. Every pending event of the application
+    /// is dequeued and sent, not only the ones of the observer, so calling this
+    /// from inside the event handler of another event loop, like the one of
+    /// winit, nests the event pump. Call it between the iterations of that loop
+    /// instead.
+    ///
+    /// # Panics
+    /// This function must be called on the main thread, otherwise it will panic.
+    ///
+    /// [NSApplication]: https://developer.apple.com/documentation/appkit/nsapplication
+    pub fn poll(&self) -> Vec<Event> {
+        let mtm = MainThreadMarker::new().expect("This function must be called on main thread");
+        let app = NSApplication::sharedApplication(mtm);
+
+        lock_state(&self.user_info).dispatcher.start_polling();
+
+        // Waiting until a date in the past returns right away once the queue is empty.
+        let until = NSDate::distantPast();
+        while let Some(event) = unsafe {
+            app.nextEventMatchingMask_untilDate_inMode_dequeue(
+                NSEventMask::Any,
+                Some(&until),
+                NSDefaultRunLoopMode,
+                true,
+            )
+        } {
+            app.sendEvent(&event);
+        }
+
+        lock_state(&self.user_info).dispatcher.take_polled_events()
+    }
+
+    /// Gets a token that stops [`MacOSDisplayObserver::run`] or
+    /// [`MacOSDisplayObserver::run_cf`] from any thread.
+    pub fn stop_token(&self) -> MacOSStopToken {
+        MacOSStopToken {
+            observer: Arc::downgrade(&self.user_info),
+        }
+    }
+}
+
+/// A handle to stop [`MacOSDisplayObserver::run`] or [`MacOSDisplayObserver::run_cf`]
+/// from any thread.
+///
+/// Since the event loop is the one of [`NSApplication`][NSApplication] or the
+/// main run loop, stopping it stops the whole application event loop.
+///
+/// [NSApplication]: https://developer.apple.com/documentation/appkit/nsapplication
+#[derive(Debug, Clone)]
+pub struct MacOSStopToken {
+    /// The state of the observer, to tell whether it was dropped.
+    observer: Weak<Mutex<UserInfo>>,
+}
+
+impl MacOSStopToken {
+    /// Stop the [`NSApplication`][NSApplication] or the run loop on the main thread,
+    /// unless the observer was dropped.
+    ///
+    /// [NSApplication]: https://developer.apple.com/documentation/appkit/nsapplication
+    pub fn stop(&self) {
+        if self.observer.strong_count() == 0 {
+            return;
+        }
+
+        DispatchQueue::main().exec_async(|| {
+            // Returns from `CFRunLoopRun` in `run_cf`. Under `NSApplication`, this
+            // only ends the current pass of the run loop, which it runs again.
+            if let Some(run_loop) = CFRunLoop::main() {
+                run_loop.stop();
+            }
+
+            let Some(mtm) = MainThreadMarker::new() else {
+                return;
+            };
+            let app = NSApplication::sharedApplication(mtm);
+            app.stop(None);
+
+            // `stop:` only takes effect after the next event is processed, so post a dummy one.
+            let event = NSEvent::otherEventWithType_location_modifierFlags_timestamp_windowNumber_context_subtype_data1_data2(
+                NSEventType::ApplicationDefined,
+                CGPoint::ZERO,
+                NSEventModifierFlags::empty(),
+                0.0,
+                0,
+                None,
+                0,
+                0,
+                0,
+            );
+            if let Some(event) = event {
+                app.postEvent_atStart(&event, true);
+            }
+        });
     }
 }
 
@@ -248,8 +1216,10 @@ impl Drop for MacOSDisplayObserver {
     fn drop(&mut self) {
         unsafe {
             let user_info = Arc::as_ptr(&self.user_info) as *mut c_void;
-            _ = CGDisplayRemoveReconfigurationCallback(Some(display_callback), user_info)
-                .into_result(());
+            _ = CGDisplayRemoveReconfigurationCallback(Some(display_callback), user_info);
+
+            NSNotificationCenter::defaultCenter()
+                .removeObserver(self.color_space_observer.0.as_ref());
         }
     }
 }
@@ -271,42 +1241,225 @@ unsafe extern "C-unwind" fn display_callback(
     // We don't own the Arc here, just borrowing the pointer.
     // The `MacOSDisplayObserver` keeps the Arc alive.
     // SAFETY: `user_info` is the pointer to the `Arc<Mutex<CallbackState>>` created in new().
-    let user_info = unsafe { &*(user_info as *const Mutex<UserInfo>) };
-    let Ok(mut user_info) = user_info.lock() else {
-        return;
+    let user_info_ptr = user_info as *const Mutex<UserInfo>;
+    let user_info = unsafe { &*user_info_ptr };
+    let mut user_info = lock_state(user_info);
+
+    let mut events = EventBatch::new();
+    // Always get the fresh state of the display when an event happens.
+    let display_snapshot = user_info.tracker.convert(get_macos_display(id));
+
+    let reason = if flags.intersects(
+        CGDisplayChangeSummaryFlags::MirrorFlag | CGDisplayChangeSummaryFlags::UnMirrorFlag,
+    ) {
+        ChangeReason::ModeChange
+    } else if flags.intersects(
+        CGDisplayChangeSummaryFlags::EnabledFlag | CGDisplayChangeSummaryFlags::DisabledFlag,
+    ) {
+        ChangeReason::Arrangement
+    } else {
+        ChangeReason::Hotplug
     };
 
-    if user_info.callback.is_some() {
-        let mut events: SmallVec<[Event; 4]> = SmallVec::new();
-        // Always get the fresh state of the display when an event happens.
-        let display_snapshot = get_macos_display(id);
-
-        if flags.contains(CGDisplayChangeSummaryFlags::AddFlag) {
-            user_info.tracker.add(display_snapshot.clone());
-            events.push(Event::Added(display_snapshot));
-        } else if flags.contains(CGDisplayChangeSummaryFlags::RemoveFlag) {
-            user_info.tracker.remove(id);
-            events.push(Event::Removed(id.into()));
-        } else if flags.contains(CGDisplayChangeSummaryFlags::MirrorFlag) {
-            events.push(Event::Mirrored(display_snapshot));
-        } else if flags.contains(CGDisplayChangeSummaryFlags::UnMirrorFlag) {
-            events.push(Event::UnMirrored(display_snapshot));
-        } else if (flags.contains(CGDisplayChangeSummaryFlags::SetModeFlag)
-            || flags.contains(CGDisplayChangeSummaryFlags::MovedFlag))
-            && let Ok(tracked_events) = user_info.tracker.track_changes()
-        {
-            for event in tracked_events {
-                events.push(event);
+    if flags.contains(CGDisplayChangeSummaryFlags::AddFlag) {
+        user_info.tracker.add(display_snapshot.clone());
+        events.push(Event::Added {
+            display: display_snapshot,
+            reason,
+        });
+        user_info.tracker.track_arrangement(&mut events);
+    } else if flags.contains(CGDisplayChangeSummaryFlags::RemoveFlag) {
+        user_info.tracker.remove(id);
+        events.push(Event::Removed {
+            id: id.into(),
+            reason,
+        });
+        user_info.tracker.track_arrangement(&mut events);
+    } else if flags.contains(CGDisplayChangeSummaryFlags::MirrorFlag) {
+        let before = user_info
+            .tracker
+            .cached_displays
+            .get(&id)
+            .filter(|display| display.is_mirrored)
+            .map(|display| display.mirror_source.clone());
+        user_info.tracker.add(display_snapshot.clone());
+
+        // A display that was already mirrored only switched to another source.
+        match before {
+            Some(before) if before != display_snapshot.mirror_source => {
+                events.push(Event::MirrorSourceChanged {
+                    after: display_snapshot.mirror_source.clone(),
+                    display: display_snapshot,
+                    before,
+                });
             }
+            Some(_) => {}
+            None => events.push(Event::Mirrored(display_snapshot)),
+        }
+    } else if flags.contains(CGDisplayChangeSummaryFlags::UnMirrorFlag) {
+        user_info.tracker.add(display_snapshot.clone());
+        events.push(Event::UnMirrored(display_snapshot));
+    } else if (flags.contains(CGDisplayChangeSummaryFlags::SetModeFlag)
+        || flags.contains(CGDisplayChangeSummaryFlags::MovedFlag))
+        && let Ok(tracked_events) = user_info.tracker.track_changes()
+    {
+        for event in tracked_events {
+            events.push(event);
+        }
+    }
+
+    // Core Graphics reports a reconfiguration once for every display it affects,
+    // so the change callback is only called once all the reports were handled.
+    let flush_change_callback = user_info.dispatcher.defer_change_callback();
+    user_info.dispatcher.dispatch(events);
+
+    let unsettled = std::mem::take(&mut user_info.tracker.unsettled);
+    if flush_change_callback || unsettled || user_info.dispatcher.next_removal_deadline().is_some()
+    {
+        // SAFETY: Same as above. The strong count is restored when the
+        // temporary `Arc` is dropped.
+        let weak_user_info = unsafe {
+            Arc::increment_strong_count(user_info_ptr);
+            Arc::downgrade(&Arc::from_raw(user_info_ptr))
+        };
+        if flush_change_callback {
+            schedule_change_callback(weak_user_info.clone());
+        }
+        if unsettled {
+            schedule_recheck(weak_user_info.clone(), 1);
         }
+        schedule_removal_flush(weak_user_info, &user_info.dispatcher);
+    }
+}
+
+/// Call the change callback held back by the reports of a reconfiguration, once
+/// the main queue is done with them.
+fn schedule_change_callback(user_info: Weak<Mutex<UserInfo>>) {
+    DispatchQueue::main().exec_async(move || {
+        // The observer may have been dropped in the meantime.
+        let Some(user_info) = user_info.upgrade() else {
+            return;
+        };
+        lock_state(&user_info).dispatcher.flush_change_callback();
+    });
+}
+
+/// Track the changes again a little later, since the last read of the displays
+/// failed or looked transitional. This backs off and gives up after a few attempts,
+/// keeping the last valid state of the displays.
+///
+/// This doesn't block the main thread, where Core Graphics calls the callback.
+fn schedule_recheck(user_info: Weak<Mutex<UserInfo>>, attempt: u32) {
+    const MAX_ATTEMPTS: u32 = 3;
+    const RETRY_DELAY: Duration = Duration::from_millis(10);
+
+    if attempt > MAX_ATTEMPTS {
+        return;
+    }
+    let Ok(when) = DispatchTime::try_from(RETRY_DELAY * attempt) else {
+        return;
+    };
 
-        if events.is_empty() {
+    _ = DispatchQueue::main().after(when, move || {
+        // The observer may have been dropped in the meantime.
+        let Some(user_info) = user_info.upgrade() else {
             return;
+        };
+        let mut guard = lock_state(&user_info);
+
+        if let Ok(events) = guard.tracker.track_changes() {
+            guard.dispatcher.dispatch(events);
+        }
+        if std::mem::take(&mut guard.tracker.unsettled) {
+            schedule_recheck(Arc::downgrade(&user_info), attempt + 1);
         }
+        schedule_removal_flush(Arc::downgrade(&user_info), &guard.dispatcher);
+    });
+}
 
-        let callback = user_info.callback.as_mut().unwrap();
-        for event in events {
-            (callback)(event);
+/// Deliver the removals held back for reconnection once the next one is due.
+fn schedule_removal_flush(user_info: Weak<Mutex<UserInfo>>, dispatcher: &EventDispatcher) {
+    let Some(deadline) = dispatcher.next_removal_deadline() else {
+        return;
+    };
+    let Ok(when) = DispatchTime::try_from(deadline.saturating_duration_since(Instant::now()))
+    else {
+        return;
+    };
+
+    _ = DispatchQueue::main().after(when, move || {
+        // The observer may have been dropped in the meantime.
+        let Some(user_info) = user_info.upgrade() else {
+            return;
+        };
+        let mut guard = lock_state(&user_info);
+
+        guard.dispatcher.flush_removals();
+        schedule_removal_flush(Arc::downgrade(&user_info), &guard.dispatcher);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A stand-in for `CGGetActiveDisplayList` listing the given displays.
+    fn display_list(
+        connected: &[MacOSDisplayId],
+    ) -> impl FnMut(u32, *mut MacOSDisplayId, &mut u32) -> CGError + '_ {
+        move |max_displays, displays, display_count| {
+            if displays.is_null() {
+                *display_count = connected.len() as u32;
+            } else {
+                let count = connected.len().min(max_displays as usize);
+                unsafe { std::ptr::copy_nonoverlapping(connected.as_ptr(), displays, count) };
+                *display_count = count as u32;
+            }
+            CGError::Success
         }
     }
+
+    #[test]
+    fn lists_every_display() {
+        // The list used to be a fixed array of 20 displays.
+        for count in [0, 1, 20, 21, 64] {
+            let connected: Vec<_> = (1..=count).collect();
+
+            let ids = list_display_ids(display_list(&connected)).unwrap();
+
+            assert_eq!(ids, connected);
+        }
+    }
+
+    #[test]
+    fn leaves_out_a_display_added_between_the_calls() {
+        let before = [1, 2];
+        let after = [1, 2, 3];
+        let mut calls = [display_list(&before), display_list(&after)].into_iter();
+        let mut current = calls.next().unwrap();
+
+        let ids = list_display_ids(|max_displays, displays, display_count| {
+            let error = current(max_displays, displays, display_count);
+            if let Some(next) = calls.next() {
+                current = next;
+            }
+            error
+        })
+        .unwrap();
+
+        assert_eq!(ids, before);
+    }
+
+    #[test]
+    fn reports_a_failed_listing() {
+        let result = list_display_ids(|_, _, _| CGError::Failure);
+
+        assert!(matches!(
+            result,
+            Err(MacOSError::CoreGraphics {
+                error: CGError::Failure,
+                ..
+            })
+        ));
+    }
 }
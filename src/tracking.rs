@@ -0,0 +1,636 @@
+//! This module contains the platform-independent helpers used by the
+//! platform-specific event trackers.
+
+use dpi::LogicalPosition;
+
+use crate::{Display, DisplayId, Event, EventBatch};
+
+/// The key used to order the events of a single reconfiguration.
+fn order_key(event: &Event) -> (u8, Option<&DisplayId>) {
+    let rank = match event {
+        Event::Removed { .. } => 0,
+        Event::AllDisplaysRemoved => 1,
+        Event::DisplaysRestored => 2,
+        Event::Added { .. } | Event::Reconnected { .. } => 3,
+        Event::RotationChanged { .. } => 4,
+        Event::SizeChanged { .. } => 5,
+        Event::OriginChanged { .. } => 6,
+        Event::Mirrored(_) => 7,
+        Event::UnMirrored(_) => 8,
+        Event::ColorProfileChanged { .. } => 9,
+        Event::MirrorSourceChanged { .. } => 10,
+        Event::VirtualOriginChanged { .. } => 11,
+        Event::PrimaryChanged { .. } => 12,
+        Event::RefreshRateChanged { .. } => 13,
+    };
+
+    (rank, event.display_id())
+}
+
+/// Get the top-left corner of the bounding box of all displays.
+fn virtual_origin<'a>(
+    displays: impl IntoIterator<Item = &'a Display>,
+) -> Option<LogicalPosition<i32>> {
+    displays.into_iter().fold(None, |origin, display| {
+        Some(match origin {
+            Some(LogicalPosition { x, y }) => {
+                LogicalPosition::new(display.origin.x.min(x), display.origin.y.min(y))
+            }
+            None => display.origin,
+        })
+    })
+}
+
+/// Add an [`Event::VirtualOriginChanged`] to the events if the top-left corner
+/// of the bounding box of the displays moved.
+///
+/// Nothing is added if there was no display before or after.
+pub(crate) fn track_virtual_origin<'a>(
+    events: &mut EventBatch,
+    before: impl IntoIterator<Item = &'a Display>,
+    after: impl IntoIterator<Item = &'a Display>,
+) {
+    if let (Some(before), Some(after)) = (virtual_origin(before), virtual_origin(after))
+        && before != after
+    {
+        events.push(Event::VirtualOriginChanged { before, after });
+    }
+}
+
+/// Add an [`Event::AllDisplaysRemoved`] or an [`Event::DisplaysRestored`] to the
+/// events if the number of displays dropped to zero or came back from it.
+pub(crate) fn track_headless_transition(
+    events: &mut EventBatch,
+    before_count: usize,
+    after_count: usize,
+) {
+    match (before_count, after_count) {
+        (1.., 0) => events.push(Event::AllDisplaysRemoved),
+        (0, 1..) => events.push(Event::DisplaysRestored),
+        _ => {}
+    }
+}
+
+/// Collect the displays of a tracker, sorted by [`DisplayId`].
+pub(crate) fn sorted_displays<'a>(displays: impl IntoIterator<Item = &'a Display>) -> Vec<Display> {
+    let mut displays: Vec<_> = displays.into_iter().cloned().collect();
+    displays.sort_by(|a, b| a.id.cmp(&b.id));
+    displays
+}
+
+/// Sort the events of a single reconfiguration into a deterministic order.
+///
+/// Removals come first, then additions and the other changes, each group
+/// ordered by [`DisplayId`].
+pub(crate) fn sort_events(events: &mut [Event]) {
+    events.sort_by(|a, b| order_key(a).cmp(&order_key(b)));
+}
+
+/// Mark the [`Event::OriginChanged`] events that only happened because the
+/// primary display moved in the arrangement.
+///
+/// This is the case when every non-primary display in `displays` (the state
+/// after the reconfiguration) shifted by the same offset. At least two shifted
+/// displays are required, since with a single one it's impossible to tell
+/// which display was actually moved.
+pub(crate) fn tag_primary_shift<'a>(
+    events: &mut [Event],
+    displays: impl IntoIterator<Item = &'a Display>,
+) {
+    let secondary_count = displays
+        .into_iter()
+        .filter(|display| !display.is_primary)
+        .count();
+
+    let mut shift = None;
+    let mut shifted_count = 0;
+
+    for event in events.iter() {
+        if let Event::OriginChanged {
+            display,
+            before,
+            after,
+            ..
+        } = event
+        {
+            if display.is_primary {
+                return;
+            }
+
+            let delta = LogicalPosition::new(after.x - before.x, after.y - before.y);
+            if *shift.get_or_insert(delta) != delta {
+                return;
+            }
+
+            shifted_count += 1;
+        }
+    }
+
+    if shifted_count < 2 || shifted_count != secondary_count {
+        return;
+    }
+
+    for event in events.iter_mut() {
+        if let Event::OriginChanged {
+            caused_by_primary_shift,
+            ..
+        } = event
+        {
+            *caused_by_primary_shift = true;
+        }
+    }
+}
+
+/// Reduce the events of several reconfigurations to their net change.
+///
+/// For each display, consecutive changes of the same kind are merged, changes
+/// that cancel each other out are dropped, and the changes of a display added
+/// in between are folded into its [`Event::Added`]. A display both added and
+/// removed in between produces no event at all.
+pub(crate) fn coalesce_events(events: impl IntoIterator<Item = Event>) -> EventBatch {
+    // The events of each display since it was last added or removed, in order.
+    let mut coalesced: Vec<Event> = Vec::new();
+
+    for event in events {
+        let id = event.display_id().cloned();
+        // Only the events since the last addition or removal of the display can be merged.
+        let start = coalesced
+            .iter()
+            .rposition(|other| {
+                other.display_id() == id.as_ref()
+                    && matches!(other, Event::Added { .. } | Event::Removed { .. })
+            })
+            .unwrap_or(0);
+        let added_at = coalesced[start..]
+            .iter()
+            .position(|other| {
+                other.display_id() == id.as_ref() && matches!(other, Event::Added { .. })
+            })
+            .map(|index| start + index);
+        let find = |coalesced: &[Event], matches: fn(&Event) -> bool| {
+            coalesced[start..]
+                .iter()
+                .position(|other| other.display_id() == id.as_ref() && matches(other))
+                .map(|index| start + index)
+        };
+
+        match event {
+            Event::Removed { .. } => {
+                // A display that was never seen before doesn't need to be reported.
+                if let Some(added_at) = added_at {
+                    coalesced.remove(added_at);
+                    coalesced.retain(|other| {
+                        other.display_id() != id.as_ref() || matches!(other, Event::Removed { .. })
+                    });
+                    continue;
+                }
+
+                // The changes of the display no longer matter once it's gone.
+                let mut index = start;
+                while index < coalesced.len() {
+                    if coalesced[index].display_id() == id.as_ref()
+                        && !matches!(coalesced[index], Event::Removed { .. })
+                    {
+                        coalesced.remove(index);
+                    } else {
+                        index += 1;
+                    }
+                }
+                coalesced.push(event);
+            }
+            // The latest state of a display added in between is reported by its addition.
+            Event::SizeChanged { display, .. }
+            | Event::OriginChanged { display, .. }
+            | Event::Mirrored(display)
+            | Event::UnMirrored(display)
+            | Event::ColorProfileChanged { display }
+            | Event::MirrorSourceChanged { display, .. }
+                if added_at.is_some() =>
+            {
+                if let Some(Event::Added {
+                    display: added_display,
+                    ..
+                }) = added_at.map(|index| &mut coalesced[index])
+                {
+                    *added_display = display;
+                }
+            }
+            Event::SizeChanged {
+                display,
+                before,
+                after,
+            } => match find(&coalesced, |other| {
+                matches!(other, Event::SizeChanged { .. })
+            }) {
+                Some(index) => {
+                    if let Event::SizeChanged {
+                        before: first_before,
+                        ..
+                    } = coalesced[index]
+                    {
+                        coalesced.remove(index);
+                        if first_before != after {
+                            coalesced.push(Event::SizeChanged {
+                                display,
+                                before: first_before,
+                                after,
+                            });
+                        }
+                    }
+                }
+                None => coalesced.push(Event::SizeChanged {
+                    display,
+                    before,
+                    after,
+                }),
+            },
+            Event::OriginChanged {
+                display,
+                before,
+                after,
+                caused_by_primary_shift,
+            } => match find(&coalesced, |other| {
+                matches!(other, Event::OriginChanged { .. })
+            }) {
+                Some(index) => {
+                    if let Event::OriginChanged {
+                        before: first_before,
+                        caused_by_primary_shift: first_caused_by_primary_shift,
+                        ..
+                    } = coalesced[index]
+                    {
+                        coalesced.remove(index);
+                        if first_before != after {
+                            coalesced.push(Event::OriginChanged {
+                                display,
+                                before: first_before,
+                                after,
+                                caused_by_primary_shift: first_caused_by_primary_shift
+                                    && caused_by_primary_shift,
+                            });
+                        }
+                    }
+                }
+                None => coalesced.push(Event::OriginChanged {
+                    display,
+                    before,
+                    after,
+                    caused_by_primary_shift,
+                }),
+            },
+            // Mirroring and unmirroring cancel each other out, while a repeated
+            // one is reported once with the latest state of the display.
+            Event::Mirrored(_) | Event::UnMirrored(_) => {
+                match find(&coalesced, |other| {
+                    matches!(other, Event::Mirrored(_) | Event::UnMirrored(_))
+                }) {
+                    Some(index) => {
+                        let first = coalesced.remove(index);
+                        if matches!(first, Event::Mirrored(_))
+                            == matches!(event, Event::Mirrored(_))
+                        {
+                            coalesced.push(event);
+                        }
+                    }
+                    None => coalesced.push(event),
+                }
+            }
+            Event::MirrorSourceChanged {
+                display,
+                before,
+                after,
+            } => match find(&coalesced, |other| {
+                matches!(other, Event::MirrorSourceChanged { .. })
+            }) {
+                Some(index) => {
+                    if let Event::MirrorSourceChanged {
+                        before: first_before,
+                        ..
+                    } = coalesced.remove(index)
+                        && first_before != after
+                    {
+                        coalesced.push(Event::MirrorSourceChanged {
+                            display,
+                            before: first_before,
+                            after,
+                        });
+                    }
+                }
+                None => coalesced.push(Event::MirrorSourceChanged {
+                    display,
+                    before,
+                    after,
+                }),
+            },
+            Event::VirtualOriginChanged { before, after } => match find(&coalesced, |other| {
+                matches!(other, Event::VirtualOriginChanged { .. })
+            }) {
+                Some(index) => {
+                    if let Event::VirtualOriginChanged {
+                        before: first_before,
+                        ..
+                    } = coalesced.remove(index)
+                        && first_before != after
+                    {
+                        coalesced.push(Event::VirtualOriginChanged {
+                            before: first_before,
+                            after,
+                        });
+                    }
+                }
+                None => coalesced.push(Event::VirtualOriginChanged { before, after }),
+            },
+            // Going headless and coming back cancel each other out.
+            Event::AllDisplaysRemoved | Event::DisplaysRestored => {
+                match find(&coalesced, |other| {
+                    matches!(other, Event::AllDisplaysRemoved | Event::DisplaysRestored)
+                }) {
+                    Some(index) => {
+                        coalesced.remove(index);
+                    }
+                    None => coalesced.push(event),
+                }
+            }
+            Event::RotationChanged {
+                display,
+                before,
+                after,
+            } => match find(&coalesced, |other| {
+                matches!(other, Event::RotationChanged { .. })
+            }) {
+                Some(index) => {
+                    if let Event::RotationChanged {
+                        before: first_before,
+                        ..
+                    } = coalesced.remove(index)
+                        && first_before != after
+                    {
+                        coalesced.push(Event::RotationChanged {
+                            display,
+                            before: first_before,
+                            after,
+                        });
+                    }
+                }
+                None => coalesced.push(Event::RotationChanged {
+                    display,
+                    before,
+                    after,
+                }),
+            },
+            Event::RefreshRateChanged {
+                display,
+                before,
+                after,
+            } => match find(&coalesced, |other| {
+                matches!(other, Event::RefreshRateChanged { .. })
+            }) {
+                Some(index) => {
+                    if let Event::RefreshRateChanged {
+                        before: first_before,
+                        ..
+                    } = coalesced.remove(index)
+                        && first_before != after
+                    {
+                        coalesced.push(Event::RefreshRateChanged {
+                            display,
+                            before: first_before,
+                            after,
+                        });
+                    }
+                }
+                None => coalesced.push(Event::RefreshRateChanged {
+                    display,
+                    before,
+                    after,
+                }),
+            },
+            // A display can only become primary and stop being it again, so
+            // two changes cancel each other out.
+            Event::PrimaryChanged { .. } => {
+                match find(&coalesced, |other| {
+                    matches!(other, Event::PrimaryChanged { .. })
+                }) {
+                    Some(index) => {
+                        coalesced.remove(index);
+                    }
+                    None => coalesced.push(event),
+                }
+            }
+            Event::ColorProfileChanged { .. } => {
+                if let Some(index) = find(&coalesced, |other| {
+                    matches!(other, Event::ColorProfileChanged { .. })
+                }) {
+                    coalesced.remove(index);
+                }
+                coalesced.push(event);
+            }
+            Event::Added { .. } | Event::Reconnected { .. } => coalesced.push(event),
+        }
+    }
+
+    let mut coalesced = EventBatch::from_vec(coalesced);
+    sort_events(&mut coalesced);
+    coalesced
+}
+
+#[cfg(test)]
+mod tests {
+    use dpi::LogicalSize;
+
+    use super::*;
+    use crate::{ChangeReason, DisplayRotation, tests::display};
+
+    fn mirrored(width: u32) -> Display {
+        Display {
+            is_mirrored: true,
+            ..display(2, (1920, 0), (width, 1080), 1.0)
+        }
+    }
+
+    fn resized(from: (u32, u32), to: (u32, u32)) -> Event {
+        Event::SizeChanged {
+            display: display(1, (0, 0), to, 1.0),
+            before: LogicalSize::new(from.0, from.1),
+            after: LogicalSize::new(to.0, to.1),
+        }
+    }
+
+    fn moved(from: (i32, i32), to: (i32, i32), caused_by_primary_shift: bool) -> Event {
+        Event::OriginChanged {
+            display: display(2, to, (1920, 1080), 1.0),
+            before: LogicalPosition::new(from.0, from.1),
+            after: LogicalPosition::new(to.0, to.1),
+            caused_by_primary_shift,
+        }
+    }
+
+    fn rotated(before: DisplayRotation, after: DisplayRotation) -> Event {
+        Event::RotationChanged {
+            display: display(1, (0, 0), (1920, 1080), 1.0),
+            before,
+            after,
+        }
+    }
+
+    #[test]
+    fn merges_consecutive_resizes() {
+        let events = coalesce_events([
+            resized((1024, 768), (1280, 720)),
+            resized((1280, 720), (1920, 1080)),
+        ]);
+
+        assert!(matches!(
+            &events[..],
+            [Event::SizeChanged { before, after, .. }]
+                if *before == LogicalSize::new(1024, 768) && *after == LogicalSize::new(1920, 1080)
+        ));
+    }
+
+    #[test]
+    fn drops_a_resize_back_to_the_start() {
+        let events = coalesce_events([
+            resized((1920, 1080), (1280, 720)),
+            resized((1280, 720), (1920, 1080)),
+        ]);
+
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn merges_moves_shifted_by_the_primary_display_only_if_all_were() {
+        let events = coalesce_events([
+            moved((1920, 0), (2560, 0), true),
+            moved((2560, 0), (2560, 200), false),
+        ]);
+
+        assert!(matches!(
+            &events[..],
+            [Event::OriginChanged { before, after, caused_by_primary_shift: false, .. }]
+                if *before == LogicalPosition::new(1920, 0)
+                    && *after == LogicalPosition::new(2560, 200)
+        ));
+    }
+
+    #[test]
+    fn drops_a_rotation_back_to_the_start() {
+        let events = coalesce_events([
+            rotated(DisplayRotation::Deg0, DisplayRotation::Deg90),
+            rotated(DisplayRotation::Deg90, DisplayRotation::Deg180),
+            rotated(DisplayRotation::Deg180, DisplayRotation::Deg0),
+        ]);
+
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn merges_refresh_rate_changes() {
+        let refresh_rate_changed = |before, after| Event::RefreshRateChanged {
+            display: display(1, (0, 0), (1920, 1080), 1.0),
+            before: Some(before),
+            after: Some(after),
+        };
+        let events = coalesce_events([
+            refresh_rate_changed(60.0, 120.0),
+            refresh_rate_changed(120.0, 144.0),
+        ]);
+
+        assert!(matches!(
+            &events[..],
+            [Event::RefreshRateChanged {
+                before: Some(60.0),
+                after: Some(144.0),
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn folds_later_changes_into_an_addition() {
+        let events = coalesce_events([
+            Event::Added {
+                display: display(1, (0, 0), (1280, 720), 1.0),
+                reason: ChangeReason::Hotplug,
+            },
+            resized((1280, 720), (1920, 1080)),
+        ]);
+
+        assert!(matches!(
+            &events[..],
+            [Event::Added { display, reason: ChangeReason::Hotplug }]
+                if display.size == LogicalSize::new(1920, 1080)
+        ));
+    }
+
+    #[test]
+    fn drops_a_display_added_and_removed() {
+        let added = display(1, (0, 0), (1920, 1080), 1.0);
+        let events = coalesce_events([
+            Event::Added {
+                display: added.clone(),
+                reason: ChangeReason::Hotplug,
+            },
+            resized((1920, 1080), (1280, 720)),
+            Event::Removed {
+                id: added.id,
+                reason: ChangeReason::Hotplug,
+            },
+        ]);
+
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn drops_the_changes_of_a_removed_display() {
+        let events = coalesce_events([
+            resized((1920, 1080), (1280, 720)),
+            Event::Removed {
+                id: display(1, (0, 0), (1920, 1080), 1.0).id,
+                reason: ChangeReason::Hotplug,
+            },
+        ]);
+
+        assert!(matches!(&events[..], [Event::Removed { .. }]));
+    }
+
+    #[test]
+    fn cancels_going_headless_and_back() {
+        let events = coalesce_events([Event::AllDisplaysRemoved, Event::DisplaysRestored]);
+
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn cancels_a_primary_change_undone() {
+        let primary_changed = |was_primary| Event::PrimaryChanged {
+            display: display(2, (1920, 0), (1920, 1080), 1.0),
+            was_primary,
+        };
+        let events = coalesce_events([primary_changed(false), primary_changed(true)]);
+
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn cancels_mirroring_undone() {
+        let events = coalesce_events([
+            Event::Mirrored(mirrored(1920)),
+            Event::UnMirrored(display(2, (1920, 0), (1920, 1080), 1.0)),
+        ]);
+
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn keeps_the_last_of_a_repeated_mirroring() {
+        let events = coalesce_events([
+            Event::Mirrored(mirrored(1920)),
+            Event::Mirrored(mirrored(1280)),
+        ]);
+
+        assert!(matches!(
+            &events[..],
+            [Event::Mirrored(display)] if display.size.width == 1280
+        ));
+    }
+}
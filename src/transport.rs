@@ -0,0 +1,144 @@
+//! This module contains the framing to send display events to another process.
+//!
+//! This is useful when the display observer can't run in the process that
+//! needs the events, e.g. in a sandboxed app that can't create a hidden window
+//! or an `NSApplication`. A helper process then runs [`DisplayObserver`]
+//! and writes the events with [`EventWriter`] to a pipe or socket, from which
+//! the main process reads them with [`EventReader`].
+//!
+//! Each event is encoded as JSON and prefixed by its length as a little-endian `u32`.
+//!
+//! ```no_run
+//! use display_config::{DisplayObserver, transport::EventWriter};
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let observer = DisplayObserver::new()?;
+//!     let mut writer = EventWriter::new(std::io::stdout());
+//!
+//!     observer.set_callback(move |event| {
+//!         writer.write_event(&event).expect("Failed to write the event");
+//!     });
+//!     observer.run()?;
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! [`DisplayObserver`]: crate::DisplayObserver
+
+use std::io::{self, Read, Write};
+
+use crate::Event;
+
+/// The largest frame accepted, far above the size of any event.
+///
+/// The length prefix comes from another process, so it isn't trusted to
+/// allocate the payload.
+pub const MAX_FRAME_LEN: usize = 1 << 20;
+
+/// Writes length-prefixed, serialized [`Event`]s to a byte stream.
+#[derive(Debug)]
+pub struct EventWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> EventWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+
+    /// Write a single event and flush the stream, so the reader receives it immediately.
+    pub fn write_event(&mut self, event: &Event) -> io::Result<()> {
+        let payload = serde_json::to_vec(event)?;
+        let len = u32::try_from(payload.len())
+            .ok()
+            .filter(|&len| len as usize <= MAX_FRAME_LEN)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "The event is too large"))?;
+
+        self.inner.write_all(&len.to_le_bytes())?;
+        self.inner.write_all(&payload)?;
+        self.inner.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Reads the [`Event`]s written by an [`EventWriter`] from a byte stream.
+#[derive(Debug)]
+pub struct EventReader<R: Read> {
+    inner: R,
+}
+
+impl<R: Read> EventReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner }
+    }
+
+    /// Read the next event.
+    ///
+    /// Returns `Ok(None)` if the stream ended cleanly before the next event, and
+    /// an error of kind [`io::ErrorKind::InvalidData`] if the frame is longer
+    /// than [`MAX_FRAME_LEN`].
+    pub fn read_event(&mut self) -> io::Result<Option<Event>> {
+        let mut len = [0; 4];
+        match self.inner.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+
+        let len = u32::from_le_bytes(len) as usize;
+        if len > MAX_FRAME_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The frame is too large",
+            ));
+        }
+
+        let mut payload = vec![0; len];
+        self.inner.read_exact(&mut payload)?;
+
+        Ok(Some(serde_json::from_slice(&payload)?))
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Iterator for EventReader<R> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_event().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_an_event() {
+        let mut writer = EventWriter::new(Vec::new());
+        writer.write_event(&Event::AllDisplaysRemoved).unwrap();
+        writer.write_event(&Event::DisplaysRestored).unwrap();
+
+        let events = EventReader::new(writer.into_inner().as_slice())
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert!(matches!(
+            events[..],
+            [Event::AllDisplaysRemoved, Event::DisplaysRestored]
+        ));
+    }
+
+    #[test]
+    fn rejects_an_oversized_frame() {
+        let len = (MAX_FRAME_LEN as u32 + 1).to_le_bytes();
+        let error = EventReader::new(&len[..]).read_event().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
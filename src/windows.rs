@@ -1,26 +1,51 @@
 //! This module contains the Windows-specific implementation of the display configuration.
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     ffi::{OsStr, OsString, c_void},
-    os::windows::ffi::OsStringExt,
-    sync::{Arc, Mutex},
+    os::windows::ffi::{OsStrExt, OsStringExt},
+    sync::{
+        Arc, Mutex, OnceLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
 };
 
 use dpi::{LogicalPosition, LogicalSize};
-use smallvec::SmallVec;
 use windows::{
     Win32::{
         Devices::Display::*,
         Foundation::*,
         Graphics::Gdi::*,
-        System::LibraryLoader::*,
-        UI::{HiDpi::*, WindowsAndMessaging::*},
+        System::{EventLog::*, LibraryLoader::*, Registry::*, Threading::*},
+        UI::{
+            ColorSystem::*,
+            Controls::{
+                POINTER_DEVICE_INFO, POINTER_DEVICE_TYPE_EXTERNAL_PEN,
+                POINTER_DEVICE_TYPE_INTEGRATED_PEN, POINTER_DEVICE_TYPE_TOUCH,
+            },
+            HiDpi::*,
+            Input::Pointer::GetPointerDevices,
+            WindowsAndMessaging::*,
+        },
     },
-    core::{BOOL, w},
+    core::{BOOL, HRESULT, HSTRING, PCWSTR, PWSTR, s, w},
 };
 
-use crate::{Display, DisplayEventCallback, Event};
+#[cfg(feature = "async")]
+use crate::dispatch::NextEvent;
+use crate::{
+    ChangeReason, ColorEncoding, CoordinateSpace, Display, DisplayChangeCallback,
+    DisplayEventCallback, DisplayEventMapper, DisplayMode, DisplayRotation, Event, EventBatch,
+    InputCapabilities, PixelFormat, Rect, RefreshRate,
+    dispatch::{EventDispatcher, EventKind, lock_state},
+    edid::Edid,
+    sort_modes,
+    tracking::{
+        sort_events, sorted_displays, tag_primary_shift, track_headless_transition,
+        track_virtual_origin,
+    },
+};
 
 /// The error type for Windows-specific operations.
 /// This is a type alias for [`windows::core::Error`][windows::core::Error].
@@ -28,9 +53,79 @@ use crate::{Display, DisplayEventCallback, Event};
 /// [windows::core::Error]: https://docs.rs/windows/latest/windows/core/struct.Error.html
 pub type WindowsError = windows::core::Error;
 
+/// The scale factors offered by the Windows display settings.
+pub(crate) const STANDARD_SCALE_FACTORS: &[f64] =
+    &[1.0, 1.25, 1.5, 1.75, 2.0, 2.25, 2.5, 3.0, 3.5, 4.0];
+
+/// Convert a null-terminated wide string buffer into an `OsString`.
+fn os_string_from_wide(buffer: &[u16]) -> OsString {
+    let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+    OsString::from_wide(&buffer[..len])
+}
+
+/// The DPI functions of `shcore.dll`, which only exists since Windows 8.1.
+///
+/// They are loaded on first use instead of being linked, so that the crate still
+/// loads on older versions of Windows.
+struct Shcore {
+    set_process_dpi_awareness: unsafe extern "system" fn(PROCESS_DPI_AWARENESS) -> HRESULT,
+    get_dpi_for_monitor:
+        unsafe extern "system" fn(HMONITOR, MONITOR_DPI_TYPE, *mut u32, *mut u32) -> HRESULT,
+}
+
+impl Shcore {
+    /// Get the functions, or `None` if they are unavailable.
+    fn get() -> Option<&'static Self> {
+        static SHCORE: OnceLock<Option<Shcore>> = OnceLock::new();
+
+        SHCORE
+            .get_or_init(|| {
+                let shcore = Self::load();
+                if shcore.is_some() {
+                    log::debug!("Using the per-monitor DPI of shcore.dll");
+                } else {
+                    log::debug!("shcore.dll is unavailable, falling back to the system DPI");
+                }
+                shcore
+            })
+            .as_ref()
+    }
+
+    fn load() -> Option<Self> {
+        let module = unsafe { LoadLibraryW(w!("shcore.dll")) }.ok()?;
+
+        // SAFETY: The signatures match the ones of the exported functions.
+        unsafe {
+            Some(Self {
+                set_process_dpi_awareness: std::mem::transmute::<
+                    unsafe extern "system" fn() -> isize,
+                    unsafe extern "system" fn(PROCESS_DPI_AWARENESS) -> HRESULT,
+                >(GetProcAddress(
+                    module,
+                    s!("SetProcessDpiAwareness"),
+                )?),
+                get_dpi_for_monitor: std::mem::transmute::<
+                    unsafe extern "system" fn() -> isize,
+                    unsafe extern "system" fn(
+                        HMONITOR,
+                        MONITOR_DPI_TYPE,
+                        *mut u32,
+                        *mut u32,
+                    ) -> HRESULT,
+                >(GetProcAddress(
+                    module,
+                    s!("GetDpiForMonitor"),
+                )?),
+            })
+        }
+    }
+}
+
 /// Sets the current process as DPI aware (Per Monitor).
 ///
 /// This function calls `SetProcessDpiAwareness(PROCESS_PER_MONITOR_DPI_AWARE)`.
+/// Before Windows 8.1, where it doesn't exist, the process is made system DPI
+/// aware with `SetProcessDPIAware` instead.
 /// It is recommended to call this function at the very beginning of the application
 /// to ensure that the display information (especially `scale_factor`) is correctly reported.
 ///
@@ -43,9 +138,14 @@ pub type WindowsError = windows::core::Error;
 /// application level very early in the process lifecycle.
 ///
 /// # Errors
-/// Returns a [`WindowsError`] if `SetProcessDpiAwareness` fails.
+/// Returns a [`WindowsError`] if `SetProcessDpiAwareness` or `SetProcessDPIAware` fails.
 pub fn set_process_per_monitor_dpi_aware() -> Result<(), WindowsError> {
-    unsafe { SetProcessDpiAwareness(PROCESS_PER_MONITOR_DPI_AWARE) }
+    match Shcore::get() {
+        Some(shcore) => {
+            unsafe { (shcore.set_process_dpi_awareness)(PROCESS_PER_MONITOR_DPI_AWARE) }.ok()
+        }
+        None => unsafe { SetProcessDPIAware() }.ok(),
+    }
 }
 
 /// A Windows-specific unique identifier for a display.
@@ -72,6 +172,32 @@ impl PartialEq for WindowsDisplayId {
 
 impl Eq for WindowsDisplayId {}
 
+impl PartialOrd for WindowsDisplayId {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for WindowsDisplayId {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.name.cmp(&other.name)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for WindowsDisplayId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.name.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for WindowsDisplayId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        OsString::deserialize(deserializer).map(Self::new)
+    }
+}
+
 impl WindowsDisplayId {
     /// Creates a new `WindowsDisplayId` from a device name string.
     pub fn new(name: OsString) -> Self {
@@ -90,12 +216,7 @@ impl WindowsDisplayId {
 
         unsafe { GetMonitorInfoW(handle, &raw mut monitor_info as _).ok()? };
 
-        let name_slice = &monitor_info.szDevice;
-        let len = name_slice
-            .iter()
-            .position(|&c| c == 0)
-            .unwrap_or(name_slice.len());
-        let name = OsString::from_wide(&monitor_info.szDevice[..len]);
+        let name = os_string_from_wide(&monitor_info.szDevice);
 
         Ok(Self {
             name: Arc::new(name),
@@ -111,58 +232,680 @@ impl WindowsDisplayId {
     pub fn device_name(&self) -> &OsStr {
         &self.name
     }
+
+    /// Get the [`LUID`][LUID] of the graphics adapter driving the display.
+    ///
+    /// Returns `None` if the display has no active display path.
+    ///
+    /// # Errors
+    /// Returns a [`WindowsError`] if querying the display configuration fails.
+    ///
+    /// [LUID]: https://learn.microsoft.com/en-us/windows/win32/api/winnt/ns-winnt-luid
+    pub fn adapter_id(&self) -> Result<Option<LUID>, WindowsError> {
+        Ok(get_paths_for_device(self.device_name())?
+            .first()
+            .map(|path| path.targetInfo.adapterId))
+    }
+}
+
+/// Query the currently active display paths from the display configuration API.
+fn query_active_paths() -> Result<Vec<DISPLAYCONFIG_PATH_INFO>, WindowsError> {
+    // The configuration may change between both calls, making the buffers too
+    // small. Microsoft recommends asking for the sizes again in that case.
+    const MAX_ATTEMPTS: u32 = 5;
+
+    let mut attempt = 1;
+    loop {
+        let mut path_count = 0;
+        let mut mode_count = 0;
+
+        unsafe {
+            GetDisplayConfigBufferSizes(QDC_ONLY_ACTIVE_PATHS, &mut path_count, &mut mode_count)
+                .ok()?;
+        }
+
+        let mut paths = vec![DISPLAYCONFIG_PATH_INFO::default(); path_count as usize];
+        let mut modes = vec![DISPLAYCONFIG_MODE_INFO::default(); mode_count as usize];
+
+        let result = unsafe {
+            QueryDisplayConfig(
+                QDC_ONLY_ACTIVE_PATHS,
+                &mut path_count,
+                paths.as_mut_ptr(),
+                &mut mode_count,
+                modes.as_mut_ptr(),
+                None,
+            )
+        };
+
+        if result == ERROR_INSUFFICIENT_BUFFER && attempt < MAX_ATTEMPTS {
+            attempt += 1;
+            continue;
+        }
+        result.ok()?;
+
+        paths.truncate(path_count as usize);
+        return Ok(paths);
+    }
+}
+
+/// Get the GDI device name (e.g. `\\.\DISPLAY1`) of the source of a display path.
+fn get_source_device_name(path: &DISPLAYCONFIG_PATH_INFO) -> Option<OsString> {
+    let mut source_name = DISPLAYCONFIG_SOURCE_DEVICE_NAME::default();
+
+    source_name.header.r#type = DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME;
+    source_name.header.size = std::mem::size_of::<DISPLAYCONFIG_SOURCE_DEVICE_NAME>() as u32;
+    source_name.header.adapterId = path.sourceInfo.adapterId;
+    source_name.header.id = path.sourceInfo.id;
+
+    if unsafe { DisplayConfigGetDeviceInfo(&mut source_name.header as *mut _) }
+        == ERROR_SUCCESS.0 as i32
+    {
+        Some(os_string_from_wide(&source_name.viewGdiDeviceName))
+    } else {
+        None
+    }
 }
 
-fn is_display_mirrored(device_name: &OsStr) -> Result<bool, WindowsError> {
-    let mut path_count = 0;
-    let mut mode_count = 0;
+/// Get the active display paths whose source is the given GDI device.
+fn get_paths_for_device(device_name: &OsStr) -> Result<Vec<DISPLAYCONFIG_PATH_INFO>, WindowsError> {
+    Ok(query_active_paths()?
+        .into_iter()
+        .filter(|path| get_source_device_name(path).is_some_and(|name| name == device_name))
+        .collect())
+}
+
+/// Get the information about the monitor at the target of a display path.
+fn get_target_device_name(
+    path: &DISPLAYCONFIG_PATH_INFO,
+) -> Option<DISPLAYCONFIG_TARGET_DEVICE_NAME> {
+    let mut target_name = DISPLAYCONFIG_TARGET_DEVICE_NAME::default();
+
+    target_name.header.r#type = DISPLAYCONFIG_DEVICE_INFO_GET_TARGET_NAME;
+    target_name.header.size = std::mem::size_of::<DISPLAYCONFIG_TARGET_DEVICE_NAME>() as u32;
+    target_name.header.adapterId = path.targetInfo.adapterId;
+    target_name.header.id = path.targetInfo.id;
+
+    (unsafe { DisplayConfigGetDeviceInfo(&mut target_name.header as *mut _) }
+        == ERROR_SUCCESS.0 as i32)
+        .then_some(target_name)
+}
+
+/// Get the text scale set in the accessibility settings ("Make text bigger").
+pub(crate) fn get_accessibility_scale() -> Option<f64> {
+    let mut percent = 0u32;
+    let mut size = std::mem::size_of::<u32>() as u32;
+
+    let result = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            w!(r"Software\Microsoft\Accessibility"),
+            w!("TextScaleFactor"),
+            RRF_RT_REG_DWORD,
+            None,
+            Some(&mut percent as *mut u32 as *mut c_void),
+            Some(&mut size),
+        )
+    };
+
+    match result {
+        ERROR_SUCCESS => Some(percent as f64 / 100.0),
+        // The value only exists once the text size was changed from the default.
+        ERROR_FILE_NOT_FOUND => Some(1.0),
+        _ => None,
+    }
+}
+
+/// Read the EDID of the monitor at the target of the display paths from the registry.
+fn read_edid(paths: &[DISPLAYCONFIG_PATH_INFO]) -> Option<Vec<u8>> {
+    let target_name = get_target_device_name(paths.first()?)?;
+
+    // The device path looks like `\\?\DISPLAY#DEL40F8#5&2a2e2c2&0&UID4352#{e6f07b5f-...}`,
+    // whose parts are the location of the monitor in the `Enum` registry key.
+    let device_path = os_string_from_wide(&target_name.monitorDevicePath);
+    let device_path = device_path.to_string_lossy();
+    let mut parts = device_path.trim_start_matches(r"\\?\").split('#');
+    let (class, hardware_id, instance_id) = (parts.next()?, parts.next()?, parts.next()?);
+
+    let key = HSTRING::from(format!(
+        r"SYSTEM\CurrentControlSet\Enum\{class}\{hardware_id}\{instance_id}\Device Parameters"
+    ));
+    let mut size = 0;
 
     unsafe {
-        GetDisplayConfigBufferSizes(QDC_ONLY_ACTIVE_PATHS, &mut path_count, &mut mode_count)
-            .ok()?;
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            &key,
+            w!("EDID"),
+            RRF_RT_REG_BINARY,
+            None,
+            None,
+            Some(&mut size),
+        )
+        .ok()
+        .ok()?;
     }
 
-    let mut paths = vec![DISPLAYCONFIG_PATH_INFO::default(); path_count as usize];
-    let mut modes = vec![DISPLAYCONFIG_MODE_INFO::default(); mode_count as usize];
+    let mut edid = vec![0u8; size as usize];
 
     unsafe {
-        QueryDisplayConfig(
-            QDC_ONLY_ACTIVE_PATHS,
-            &mut path_count,
-            paths.as_mut_ptr(),
-            &mut mode_count,
-            modes.as_mut_ptr(),
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            &key,
+            w!("EDID"),
+            RRF_RT_REG_BINARY,
             None,
+            Some(edid.as_mut_ptr() as *mut c_void),
+            Some(&mut size),
         )
+        .ok()
         .ok()?;
     }
 
-    let mut match_count = 0;
-    for path in paths.iter().take(path_count as usize) {
-        let mut source_name = DISPLAYCONFIG_SOURCE_DEVICE_NAME::default();
+    edid.truncate(size as usize);
+    Some(edid)
+}
 
-        source_name.header.r#type = DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME;
-        source_name.header.size = std::mem::size_of::<DISPLAYCONFIG_SOURCE_DEVICE_NAME>() as u32;
-        source_name.header.adapterId = path.sourceInfo.adapterId;
-        source_name.header.id = path.sourceInfo.id;
+/// Get the parsed EDID of the monitor shown as the given display.
+pub(crate) fn get_edid(id: &WindowsDisplayId) -> Option<Edid> {
+    let paths = get_paths_for_device(id.device_name()).ok()?;
+    read_edid(&paths).and_then(Edid::new)
+}
 
-        if unsafe { DisplayConfigGetDeviceInfo(&mut source_name.header as *mut _) }
-            == ERROR_SUCCESS.0 as i32
-        {
-            let name_slice = &source_name.viewGdiDeviceName;
-            let len = name_slice
+/// Get the name of the monitor shown in the display settings, e.g. `DELL U2720Q`.
+///
+/// Windows leaves the friendly name empty for some monitors, so the product
+/// name from the EDID is used for them.
+pub(crate) fn get_friendly_name(id: &WindowsDisplayId) -> Option<String> {
+    let paths = get_paths_for_device(id.device_name()).ok()?;
+    let target_name = get_target_device_name(paths.first()?)?;
+    let name = os_string_from_wide(&target_name.monitorFriendlyDeviceName);
+
+    if name.is_empty() {
+        read_edid(&paths).and_then(Edid::new)?.product_name()
+    } else {
+        Some(name.to_string_lossy().into_owned())
+    }
+}
+
+/// Get a key of the display that stays the same across reconnections and
+/// reboots: the device path of the monitor, or the GDI device name if the
+/// monitor can't be found.
+pub(crate) fn get_persistent_key(id: &WindowsDisplayId) -> String {
+    get_paths_for_device(id.device_name())
+        .ok()
+        .and_then(|paths| get_target_device_name(paths.first()?))
+        .map(|target_name| os_string_from_wide(&target_name.monitorDevicePath))
+        .unwrap_or_else(|| id.device_name().to_owned())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// The clone set a display belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MirrorGroupInfo {
+    /// The number of monitors in the clone set, at least `1`.
+    pub(crate) count: usize,
+}
+
+impl MirrorGroupInfo {
+    /// Get the clone set from the active display paths of a display.
+    fn from_paths(paths: &[DISPLAYCONFIG_PATH_INFO]) -> Self {
+        Self {
+            count: paths.len().max(1),
+        }
+    }
+}
+
+/// Get the clone set of the display with the given GDI device name.
+pub(crate) fn mirror_group_info(device_name: &OsStr) -> Result<MirrorGroupInfo, WindowsError> {
+    Ok(MirrorGroupInfo::from_paths(&get_paths_for_device(
+        device_name,
+    )?))
+}
+
+fn is_display_mirrored(paths: &[DISPLAYCONFIG_PATH_INFO]) -> bool {
+    MirrorGroupInfo::from_paths(paths).count > 1
+}
+
+/// Get the number of monitors in the clone set of the display, including itself.
+///
+/// # Errors
+/// Returns a [`WindowsError`] if querying the display configuration fails.
+pub fn get_mirror_set_size(id: &WindowsDisplayId) -> Result<usize, WindowsError> {
+    Ok(mirror_group_info(id.device_name())?.count)
+}
+
+fn is_display_builtin(paths: &[DISPLAYCONFIG_PATH_INFO]) -> bool {
+    paths.iter().any(|path| {
+        matches!(
+            path.targetInfo.outputTechnology,
+            DISPLAYCONFIG_OUTPUT_TECHNOLOGY_INTERNAL
+                | DISPLAYCONFIG_OUTPUT_TECHNOLOGY_DISPLAYPORT_EMBEDDED
+                | DISPLAYCONFIG_OUTPUT_TECHNOLOGY_UDI_EMBEDDED
+        )
+    })
+}
+
+/// Get the path of the ICM profile assigned to the given GDI device.
+fn get_color_profile(device_name: &OsStr) -> Option<OsString> {
+    let device_name: Vec<u16> = device_name.encode_wide().chain(Some(0)).collect();
+    let hdc = unsafe {
+        CreateDCW(
+            w!("DISPLAY"),
+            PCWSTR(device_name.as_ptr()),
+            PCWSTR::null(),
+            None,
+        )
+    };
+    if hdc.is_invalid() {
+        return None;
+    }
+
+    let mut size = MAX_PATH;
+    let mut buffer = vec![0u16; size as usize];
+    let found = unsafe { GetICMProfileW(hdc, &mut size, Some(PWSTR(buffer.as_mut_ptr()))) };
+
+    unsafe {
+        _ = DeleteDC(hdc);
+    }
+
+    found.as_bool().then(|| os_string_from_wide(&buffer))
+}
+
+/// The hardware IDs of well-known virtual display drivers that don't report
+/// themselves as indirect virtual displays.
+const VIRTUAL_DISPLAY_HARDWARE_IDS: &[&str] = &["MTT1337", "PSCCDD0"];
+
+fn is_display_virtual(paths: &[DISPLAYCONFIG_PATH_INFO]) -> bool {
+    paths.iter().any(|path| {
+        if path.targetInfo.outputTechnology == DISPLAYCONFIG_OUTPUT_TECHNOLOGY_INDIRECT_VIRTUAL {
+            return true;
+        }
+
+        get_target_device_name(path).is_some_and(|target_name| {
+            let device_path = os_string_from_wide(&target_name.monitorDevicePath);
+            let device_path = device_path.to_string_lossy();
+            VIRTUAL_DISPLAY_HARDWARE_IDS
                 .iter()
-                .position(|&c| c == 0)
-                .unwrap_or(name_slice.len());
-            let name = OsString::from_wide(&name_slice[..len]);
+                .any(|hardware_id| device_path.contains(&format!("#{hardware_id}#")))
+        })
+    })
+}
+
+/// Get the current display settings of the given GDI device.
+fn get_current_settings(device_name: &OsStr) -> Option<DEVMODEW> {
+    let device_name: Vec<u16> = device_name.encode_wide().chain(Some(0)).collect();
+    let mut dev_mode = DEVMODEW {
+        dmSize: std::mem::size_of::<DEVMODEW>() as _,
+        ..Default::default()
+    };
+
+    unsafe {
+        EnumDisplaySettingsW(
+            PCWSTR(device_name.as_ptr()),
+            ENUM_CURRENT_SETTINGS,
+            &mut dev_mode,
+        )
+    }
+    .as_bool()
+    .then_some(dev_mode)
+}
+
+/// Get the advanced color information of the monitor at the target of the display path.
+fn get_advanced_color_info(
+    path: &DISPLAYCONFIG_PATH_INFO,
+) -> Option<DISPLAYCONFIG_GET_ADVANCED_COLOR_INFO> {
+    let mut color_info = DISPLAYCONFIG_GET_ADVANCED_COLOR_INFO::default();
+
+    color_info.header.r#type = DISPLAYCONFIG_DEVICE_INFO_GET_ADVANCED_COLOR_INFO;
+    color_info.header.size = std::mem::size_of::<DISPLAYCONFIG_GET_ADVANCED_COLOR_INFO>() as u32;
+    color_info.header.adapterId = path.targetInfo.adapterId;
+    color_info.header.id = path.targetInfo.id;
+
+    (unsafe { DisplayConfigGetDeviceInfo(&mut color_info.header as *mut _) }
+        == ERROR_SUCCESS.0 as i32)
+        .then_some(color_info)
+}
+
+/// Whether the monitor at the target of the display path supports advanced color (HDR).
+fn is_advanced_color_supported(path: &DISPLAYCONFIG_PATH_INFO) -> bool {
+    // SAFETY: Every bit pattern is a valid `u32`. Bit 0 is `advancedColorSupported`.
+    get_advanced_color_info(path)
+        .is_some_and(|color_info| unsafe { color_info.Anonymous.value & 0x1 != 0 })
+}
+
+/// Get the pixel format the display is driven with.
+pub(crate) fn get_pixel_format(id: &WindowsDisplayId) -> Option<PixelFormat> {
+    let paths = get_paths_for_device(id.device_name()).ok()?;
+    let color_info = get_advanced_color_info(paths.first()?)?;
+
+    let encoding = match color_info.colorEncoding {
+        DISPLAYCONFIG_COLOR_ENCODING_RGB => ColorEncoding::Rgb,
+        DISPLAYCONFIG_COLOR_ENCODING_YCBCR444 => ColorEncoding::YCbCr444,
+        DISPLAYCONFIG_COLOR_ENCODING_YCBCR422 => ColorEncoding::YCbCr422,
+        DISPLAYCONFIG_COLOR_ENCODING_YCBCR420 => ColorEncoding::YCbCr420,
+        DISPLAYCONFIG_COLOR_ENCODING_INTENSITY => ColorEncoding::Intensity,
+        _ => return None,
+    };
+
+    Some(PixelFormat {
+        encoding,
+        bits_per_channel: color_info.bitsPerColorChannel,
+    })
+}
+
+/// Whether the display supports advanced color, i.e. can show HDR content.
+pub(crate) fn is_hdr_capable(id: &WindowsDisplayId) -> bool {
+    get_paths_for_device(id.device_name())
+        .is_ok_and(|paths| paths.first().is_some_and(is_advanced_color_supported))
+}
+
+/// Get the display modes supported by the given GDI device, without duplicates.
+pub(crate) fn get_available_modes(id: &WindowsDisplayId) -> Vec<DisplayMode> {
+    let device_name: Vec<u16> = id.device_name().encode_wide().chain(Some(0)).collect();
+    let hdr_capable = is_hdr_capable(id);
+    let mut modes = Vec::new();
+
+    for index in 0.. {
+        let mut dev_mode = DEVMODEW {
+            dmSize: std::mem::size_of::<DEVMODEW>() as _,
+            ..Default::default()
+        };
 
-            if name == device_name {
-                match_count += 1;
+        let found = unsafe {
+            EnumDisplaySettingsW(
+                PCWSTR(device_name.as_ptr()),
+                ENUM_DISPLAY_SETTINGS_MODE(index),
+                &mut dev_mode,
+            )
+        };
+        if !found.as_bool() {
+            break;
+        }
+
+        let frequency = get_frequency(&dev_mode);
+        modes.push(DisplayMode {
+            size: LogicalSize::new(dev_mode.dmPelsWidth, dev_mode.dmPelsHeight),
+            refresh_rate: frequency.map(|frequency| frequency as f64),
+            exact_refresh_rate: frequency.map(RefreshRate::from_truncated_hz),
+            hdr_capable,
+        });
+    }
+
+    sort_modes(&mut modes);
+    modes
+}
+
+/// Get the refresh rate of a mode in whole Hz.
+fn get_frequency(dev_mode: &DEVMODEW) -> Option<u32> {
+    // 0 and 1 stand for the default refresh rate of the hardware.
+    (dev_mode.dmDisplayFrequency > 1).then_some(dev_mode.dmDisplayFrequency)
+}
+
+fn get_rotation(dev_mode: &DEVMODEW) -> DisplayRotation {
+    // SAFETY: `dmDisplayOrientation` is the active field for display devices.
+    match unsafe { dev_mode.Anonymous1.Anonymous2.dmDisplayOrientation } {
+        DMDO_90 => DisplayRotation::Deg90,
+        DMDO_180 => DisplayRotation::Deg180,
+        DMDO_270 => DisplayRotation::Deg270,
+        _ => DisplayRotation::Deg0,
+    }
+}
+
+/// Get the rotation applied by the display path, which is how the GPU scans out the image.
+pub(crate) fn get_scanout_rotation(id: &WindowsDisplayId) -> Option<DisplayRotation> {
+    let paths = get_paths_for_device(id.device_name()).ok()?;
+
+    match paths.first()?.targetInfo.rotation {
+        DISPLAYCONFIG_ROTATION_IDENTITY => Some(DisplayRotation::Deg0),
+        DISPLAYCONFIG_ROTATION_ROTATE90 => Some(DisplayRotation::Deg90),
+        DISPLAYCONFIG_ROTATION_ROTATE180 => Some(DisplayRotation::Deg180),
+        DISPLAYCONFIG_ROTATION_ROTATE270 => Some(DisplayRotation::Deg270),
+        _ => None,
+    }
+}
+
+/// Get the touch and pen digitizers mapped to the given monitor.
+fn get_input_capabilities(h_monitor: HMONITOR) -> InputCapabilities {
+    let mut capabilities = InputCapabilities::default();
+
+    // Skip the enumeration on the common machines without any digitizer.
+    if unsafe { GetSystemMetrics(SM_DIGITIZER) } == 0 {
+        return capabilities;
+    }
+
+    let mut count = 0;
+    if unsafe { GetPointerDevices(&mut count, None) }.is_err() {
+        return capabilities;
+    }
+
+    let mut devices = vec![POINTER_DEVICE_INFO::default(); count as usize];
+    if unsafe { GetPointerDevices(&mut count, Some(devices.as_mut_ptr())) }.is_err() {
+        return capabilities;
+    }
+    devices.truncate(count as usize);
+
+    for device in devices.iter().filter(|device| device.monitor == h_monitor) {
+        match device.pointerDeviceType {
+            POINTER_DEVICE_TYPE_TOUCH => capabilities.touch = true,
+            POINTER_DEVICE_TYPE_INTEGRATED_PEN | POINTER_DEVICE_TYPE_EXTERNAL_PEN => {
+                capabilities.pen = true
             }
+            _ => {}
+        }
+    }
+
+    capabilities
+}
+
+/// Find the `HMONITOR` of the display with the given ID.
+fn find_monitor(id: &WindowsDisplayId) -> Option<HMONITOR> {
+    unsafe extern "system" fn find_monitor_proc(
+        h_monitor: HMONITOR,
+        _hdc: HDC,
+        _rect: *mut RECT,
+        user_data: LPARAM,
+    ) -> BOOL {
+        let (id, found) =
+            unsafe { &mut *(user_data.0 as *mut (&WindowsDisplayId, Option<HMONITOR>)) };
+
+        if WindowsDisplayId::from_handle(h_monitor).is_ok_and(|other| other == **id) {
+            *found = Some(h_monitor);
+            return false.into();
         }
+
+        true.into()
+    }
+
+    let mut user_data = (id, None);
+    // Stopping the enumeration early makes it report a failure, so the result is ignored.
+    unsafe {
+        _ = EnumDisplayMonitors(
+            None,
+            None,
+            Some(find_monitor_proc),
+            LPARAM(&raw mut user_data as isize),
+        );
+    }
+
+    user_data.1
+}
+
+/// The brightness of a monitor controlled over DDC/CI, in the units of the monitor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MonitorBrightness {
+    /// The lowest brightness the monitor supports.
+    pub min: u32,
+    /// The current brightness.
+    pub current: u32,
+    /// The highest brightness the monitor supports.
+    pub max: u32,
+}
+
+impl MonitorBrightness {
+    /// Get the current brightness from `0.0` to `1.0` within the range of the monitor.
+    pub fn normalized(&self) -> Option<f32> {
+        (self.max > self.min).then(|| {
+            let current = self.current.clamp(self.min, self.max);
+            (current - self.min) as f32 / (self.max - self.min) as f32
+        })
     }
 
-    Ok(match_count > 1)
+    /// Get the value in the units of the monitor for a brightness from `0.0` to `1.0`.
+    pub fn denormalize(&self, brightness: f32) -> u32 {
+        let range = self.max.saturating_sub(self.min) as f32;
+        self.min + (brightness.clamp(0.0, 1.0) * range).round() as u32
+    }
+}
+
+/// The physical monitors behind an `HMONITOR`, which are released when dropped.
+struct PhysicalMonitors(Vec<PHYSICAL_MONITOR>);
+
+impl PhysicalMonitors {
+    fn new(id: &WindowsDisplayId) -> Result<Self, WindowsError> {
+        let h_monitor = find_monitor(id).ok_or_else(|| {
+            WindowsError::new(
+                ERROR_NOT_FOUND.to_hresult(),
+                "The display is not connected.",
+            )
+        })?;
+
+        let mut count = 0;
+        unsafe { GetNumberOfPhysicalMonitorsFromHMONITOR(h_monitor, &mut count)? };
+
+        let mut monitors = vec![PHYSICAL_MONITOR::default(); count as usize];
+        unsafe { GetPhysicalMonitorsFromHMONITOR(h_monitor, &mut monitors)? };
+
+        Ok(Self(monitors))
+    }
+}
+
+impl Drop for PhysicalMonitors {
+    fn drop(&mut self) {
+        unsafe { _ = DestroyPhysicalMonitors(&self.0) };
+    }
+}
+
+/// The error returned when a monitor doesn't support brightness control over DDC/CI.
+fn brightness_not_supported() -> WindowsError {
+    WindowsError::new(
+        ERROR_NOT_SUPPORTED.to_hresult(),
+        "The monitor doesn't support brightness control over DDC/CI.",
+    )
+}
+
+/// Get the brightness of the display over DDC/CI.
+///
+/// Talking to the monitor is slow, so this may block for tens of milliseconds.
+///
+/// # Errors
+/// Returns a [`WindowsError`] if the display is not connected, or if the monitor
+/// doesn't support DDC/CI or has it disabled in its settings.
+pub fn get_monitor_brightness(id: &WindowsDisplayId) -> Result<MonitorBrightness, WindowsError> {
+    let monitors = PhysicalMonitors::new(id)?;
+    let monitor = monitors.0.first().ok_or_else(brightness_not_supported)?;
+
+    let mut brightness = MonitorBrightness {
+        min: 0,
+        current: 0,
+        max: 0,
+    };
+    let succeeded = unsafe {
+        GetMonitorBrightness(
+            monitor.hPhysicalMonitor,
+            &mut brightness.min,
+            &mut brightness.current,
+            &mut brightness.max,
+        )
+    } != 0;
+
+    if succeeded {
+        Ok(brightness)
+    } else {
+        Err(brightness_not_supported())
+    }
+}
+
+/// Set the brightness of the display over DDC/CI, in the units of the monitor.
+///
+/// If several physical monitors are behind the display, e.g. when it's
+/// duplicated, all of them are set.
+///
+/// # Errors
+/// Returns a [`WindowsError`] if the display is not connected, or if the monitor
+/// doesn't support DDC/CI or has it disabled in its settings.
+pub fn set_monitor_brightness(id: &WindowsDisplayId, brightness: u32) -> Result<(), WindowsError> {
+    let monitors = PhysicalMonitors::new(id)?;
+    if monitors.0.is_empty() {
+        return Err(brightness_not_supported());
+    }
+
+    for monitor in &monitors.0 {
+        if unsafe { SetMonitorBrightness(monitor.hPhysicalMonitor, brightness) } == 0 {
+            return Err(brightness_not_supported());
+        }
+    }
+
+    Ok(())
+}
+
+/// The VCP code of the input source in the Monitor Control Command Set.
+const VCP_INPUT_SOURCE: u8 = 0x60;
+
+/// Get the input source of the display over DDC/CI, as the value of VCP code `0x60`.
+///
+/// Returns `None` if the monitor doesn't support DDC/CI or has it disabled in
+/// its settings. Talking to the monitor is slow, so this may block for tens of
+/// milliseconds.
+///
+/// # Errors
+/// Returns a [`WindowsError`] if the display is not connected.
+pub fn get_monitor_input_source(id: &WindowsDisplayId) -> Result<Option<u8>, WindowsError> {
+    let monitors = PhysicalMonitors::new(id)?;
+    let Some(monitor) = monitors.0.first() else {
+        return Ok(None);
+    };
+
+    let mut current = 0;
+    let succeeded = unsafe {
+        GetVCPFeatureAndVCPFeatureReply(
+            monitor.hPhysicalMonitor,
+            VCP_INPUT_SOURCE,
+            None,
+            &mut current,
+            None,
+        )
+    } != 0;
+
+    // Some monitors put vendor-specific flags in the high byte.
+    Ok(succeeded.then_some((current & 0xFF) as u8))
+}
+
+/// Switch the input source of the display over DDC/CI, as the value of VCP code `0x60`.
+///
+/// Common values are `0x0F` for DisplayPort 1, `0x10` for DisplayPort 2,
+/// `0x11` for HDMI 1 and `0x12` for HDMI 2, though monitors deviate from them.
+///
+/// # Errors
+/// Returns a [`WindowsError`] if the display is not connected, or if the monitor
+/// doesn't support DDC/CI or has it disabled in its settings.
+pub fn set_monitor_input_source(id: &WindowsDisplayId, source: u8) -> Result<(), WindowsError> {
+    let not_supported = || {
+        WindowsError::new(
+            ERROR_NOT_SUPPORTED.to_hresult(),
+            "The monitor doesn't support switching the input source over DDC/CI.",
+        )
+    };
+
+    let monitors = PhysicalMonitors::new(id)?;
+    let monitor = monitors.0.first().ok_or_else(not_supported)?;
+
+    if unsafe { SetVCPFeature(monitor.hPhysicalMonitor, VCP_INPUT_SOURCE, source as u32) } == 0 {
+        return Err(not_supported());
+    }
+
+    Ok(())
 }
 
 fn get_scale_factor(hdc: HDC, h_monitor: HMONITOR) -> f64 {
@@ -171,7 +914,12 @@ fn get_scale_factor(hdc: HDC, h_monitor: HMONITOR) -> f64 {
 
     let mut dpi_x = 0;
     let mut dpi_y = 0;
-    let result = unsafe { GetDpiForMonitor(h_monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y) };
+    let result = match Shcore::get() {
+        Some(shcore) => unsafe {
+            (shcore.get_dpi_for_monitor)(h_monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y)
+        },
+        None => E_NOTIMPL,
+    };
 
     if result.is_err() {
         dpi_x = if unsafe { IsProcessDPIAware().as_bool() } {
@@ -184,6 +932,45 @@ fn get_scale_factor(hdc: HDC, h_monitor: HMONITOR) -> f64 {
     dpi_x as f64 / USER_DEFAULT_SCREEN_DPI as f64
 }
 
+/// Get the bounds of the primary display, divided by its scale factor.
+pub(crate) fn get_primary_bounds() -> Result<Rect, WindowsError> {
+    // The primary display is the one at the origin of the virtual screen.
+    let h_monitor = unsafe { MonitorFromPoint(POINT::default(), MONITOR_DEFAULTTOPRIMARY) };
+
+    let mut monitor_info = MONITORINFO {
+        cbSize: std::mem::size_of::<MONITORINFO>() as _,
+        ..Default::default()
+    };
+    unsafe { GetMonitorInfoW(h_monitor, &mut monitor_info).ok()? };
+
+    let hdc = unsafe { GetDC(None) };
+    let scale_factor = get_scale_factor(hdc, h_monitor);
+    unsafe { ReleaseDC(None, hdc) };
+
+    let rect = monitor_info.rcMonitor;
+    Ok(Rect {
+        origin: LogicalPosition::new(
+            rect.left as f64 / scale_factor,
+            rect.top as f64 / scale_factor,
+        ),
+        size: LogicalSize::new(
+            (rect.right - rect.left) as f64 / scale_factor,
+            (rect.bottom - rect.top) as f64 / scale_factor,
+        ),
+    })
+}
+
+/// Get the size of a monitor rectangle.
+///
+/// Monitors left of or above the primary one have negative edges, e.g.
+/// `left: -1920, right: 0`, which still give a positive size. The edges are
+/// subtracted as `i64` so the far ends of the coordinate range can't overflow,
+/// and an inverted rectangle gives a zero size, which [`Display::is_sane`] rejects.
+fn rect_size(rect: &RECT) -> LogicalSize<u32> {
+    let length = |start: i32, end: i32| u32::try_from(end as i64 - start as i64).unwrap_or(0);
+    LogicalSize::new(length(rect.left, rect.right), length(rect.top, rect.bottom))
+}
+
 struct EnumDisplayMonitorsUserData {
     displays: Vec<Display>,
     result: Result<(), WindowsError>,
@@ -211,32 +998,40 @@ unsafe extern "system" fn monitor_enum_proc(
         return true.into(); // Skip this monitor but continue enumeration
     }
 
-    let name_slice = &monitor_info.szDevice;
-    let len = name_slice
-        .iter()
-        .position(|&c| c == 0)
-        .unwrap_or(name_slice.len());
-    let device_name = OsString::from_wide(&monitor_info.szDevice[..len]);
+    let device_name = os_string_from_wide(&monitor_info.szDevice);
     let id = WindowsDisplayId::new(device_name);
 
     let origin = LogicalPosition::new(
         monitor_info.monitorInfo.rcMonitor.left,
         monitor_info.monitorInfo.rcMonitor.top,
     );
-    let size = LogicalSize::new(
-        (monitor_info.monitorInfo.rcMonitor.right - monitor_info.monitorInfo.rcMonitor.left) as u32,
-        (monitor_info.monitorInfo.rcMonitor.bottom - monitor_info.monitorInfo.rcMonitor.top) as u32,
-    );
+    let size = rect_size(&monitor_info.monitorInfo.rcMonitor);
     let is_primary = (monitor_info.monitorInfo.dwFlags & MONITORINFOF_PRIMARY) != 0;
 
-    let is_mirrored = match is_display_mirrored(id.device_name()) {
-        Ok(value) => value,
+    let paths = match get_paths_for_device(id.device_name()) {
+        Ok(paths) => paths,
         Err(e) => {
             user_data.result = Err(e);
             return false.into();
         }
     };
+    let is_mirrored = is_display_mirrored(&paths);
+    let is_builtin = is_display_builtin(&paths);
+    let is_virtual = is_display_virtual(&paths);
+    let edid = read_edid(&paths).and_then(Edid::new);
+    let vrr = edid.as_ref().map(Edid::vrr_info);
+    let subpixel = edid.as_ref().map(Edid::subpixel_layout);
+    let current_settings = get_current_settings(id.device_name());
+    let rotation = current_settings
+        .as_ref()
+        .map(get_rotation)
+        .unwrap_or_default();
+    let refresh_rate = current_settings
+        .as_ref()
+        .and_then(get_frequency)
+        .map(f64::from);
     let scale_factor = get_scale_factor(hdc, h_monitor);
+    let input_capabilities = get_input_capabilities(h_monitor);
 
     user_data.displays.push(Display {
         id: id.into(),
@@ -245,12 +1040,24 @@ unsafe extern "system" fn monitor_enum_proc(
         scale_factor,
         is_primary,
         is_mirrored,
+        // Clone sets have no original display, every member shows the same source.
+        mirror_source: None,
+        is_builtin,
+        is_virtual,
+        rotation,
+        refresh_rate,
+        vrr,
+        subpixel,
+        input_capabilities,
     });
 
     true.into()
 }
 
 /// Get a list of all currently active Windows displays.
+///
+/// On some multi-GPU setups, the same monitor is enumerated more than once
+/// with the same device path. Only the first of them is kept.
 pub fn get_windows_displays() -> Result<Vec<Display>, WindowsError> {
     let mut user_data: EnumDisplayMonitorsUserData = EnumDisplayMonitorsUserData {
         displays: Vec::new(),
@@ -267,42 +1074,105 @@ pub fn get_windows_displays() -> Result<Vec<Display>, WindowsError> {
         .ok()?;
     };
 
-    user_data.result.map(|_| user_data.displays)
+    user_data.result?;
+
+    let mut seen_ids = HashSet::new();
+    let mut displays = user_data.displays;
+    displays.retain(|display| seen_ids.insert(display.id.clone()));
+
+    Ok(displays)
 }
 
 struct EventTracker {
     cached_displays: HashMap<WindowsDisplayId, Display>,
+    color_profiles: HashMap<WindowsDisplayId, Option<OsString>>,
+    /// The displays before the last tracked change.
+    previous_displays: HashMap<WindowsDisplayId, Display>,
+    coordinate_space: CoordinateSpace,
+    /// Whether a monitor arrived or was removed since the last display was
+    /// added or removed, or since the last `WM_DISPLAYCHANGE`.
+    hotplug_pending: bool,
 }
 
 impl EventTracker {
-    fn new() -> Result<Self, WindowsError> {
-        let mut tracker = Self {
-            cached_displays: HashMap::new(),
-        };
-        tracker.cached_displays = tracker.collect_new_cached_state()?;
+    fn new(coordinate_space: CoordinateSpace) -> Result<Self, WindowsError> {
+        let cached_displays = Self::collect_new_cached_state(coordinate_space)?;
+        let mut tracker = Self::from_state(cached_displays, coordinate_space);
+        tracker.track_color_profiles();
 
         Ok(tracker)
     }
 
-    fn collect_new_cached_state(&self) -> Result<HashMap<WindowsDisplayId, Display>, WindowsError> {
-        let displays = get_windows_displays()?;
+    /// Create a tracker that starts from the given displays instead of the
+    /// current ones, so the tracking can be driven without real hardware.
+    pub(crate) fn from_state(
+        cached_displays: HashMap<WindowsDisplayId, Display>,
+        coordinate_space: CoordinateSpace,
+    ) -> Self {
+        Self {
+            previous_displays: cached_displays.clone(),
+            cached_displays,
+            color_profiles: HashMap::new(),
+            coordinate_space,
+            hotplug_pending: false,
+        }
+    }
+
+    fn collect_new_cached_state(
+        coordinate_space: CoordinateSpace,
+    ) -> Result<HashMap<WindowsDisplayId, Display>, WindowsError> {
+        let mut displays = get_windows_displays()?;
+        coordinate_space.convert(&mut displays);
         let mut cached_state = HashMap::new();
 
         for display in displays {
-            let win_id = display.id.windows_id();
-            cached_state.insert(win_id.clone(), display);
+            let win_id = display.id.windows_id().clone();
+            let duplicate = cached_state.insert(win_id, display);
+            debug_assert!(duplicate.is_none(), "display enumerated twice");
         }
 
         Ok(cached_state)
     }
 
-    fn track_events(&mut self) -> Result<SmallVec<[Event; 10]>, WindowsError> {
-        let new_cached_state = self.collect_new_cached_state()?;
+    fn track_events(&mut self) -> Result<EventBatch, WindowsError> {
+        Ok(self.track_state(Self::collect_new_cached_state(self.coordinate_space)?))
+    }
+
+    /// Replace the cached displays with the given ones, as done on `WM_DISPLAYCHANGE`,
+    /// and get the events of the difference.
+    pub(crate) fn track_state(
+        &mut self,
+        mut new_cached_state: HashMap<WindowsDisplayId, Display>,
+    ) -> EventBatch {
+        // Don't report a half-applied configuration. Displays keep their last sane
+        // state, and new ones are reported once they settle.
+        new_cached_state.retain(|id, display| {
+            if display.is_sane() {
+                return true;
+            }
+
+            match self.cached_displays.get(id) {
+                Some(before_display) => {
+                    *display = before_display.clone();
+                    true
+                }
+                None => false,
+            }
+        });
+
         let before = std::mem::replace(&mut self.cached_displays, new_cached_state);
-        let mut events = SmallVec::new();
+        let mut events = EventBatch::new();
 
         for (id, before_display) in before.iter() {
             if let Some(after_display) = self.cached_displays.get(id) {
+                if before_display.rotation != after_display.rotation {
+                    events.push(Event::RotationChanged {
+                        display: (*after_display).clone(),
+                        before: before_display.rotation,
+                        after: after_display.rotation,
+                    });
+                }
+
                 if before_display.size != after_display.size {
                     events.push(Event::SizeChanged {
                         display: (*after_display).clone(),
@@ -316,6 +1186,22 @@ impl EventTracker {
                         display: (*after_display).clone(),
                         before: before_display.origin,
                         after: after_display.origin,
+                        caused_by_primary_shift: false,
+                    });
+                }
+
+                if before_display.refresh_rate != after_display.refresh_rate {
+                    events.push(Event::RefreshRateChanged {
+                        display: (*after_display).clone(),
+                        before: before_display.refresh_rate,
+                        after: after_display.refresh_rate,
+                    });
+                }
+
+                if before_display.is_primary != after_display.is_primary {
+                    events.push(Event::PrimaryChanged {
+                        display: (*after_display).clone(),
+                        was_primary: before_display.is_primary,
                     });
                 }
 
@@ -329,23 +1215,127 @@ impl EventTracker {
                     events.push(event);
                 }
             } else {
-                events.push(Event::Removed(id.clone().into()));
+                events.push(Event::Removed {
+                    id: id.clone().into(),
+                    reason: ChangeReason::Arrangement,
+                });
             }
         }
 
         for (id, after_display) in &self.cached_displays {
             if !before.contains_key(id) {
-                events.push(Event::Added((*after_display).clone()));
+                events.push(Event::Added {
+                    display: (*after_display).clone(),
+                    reason: ChangeReason::Arrangement,
+                });
+            }
+        }
+
+        self.tag_change_reasons(&mut events);
+        track_headless_transition(&mut events, before.len(), self.cached_displays.len());
+        track_virtual_origin(&mut events, before.values(), self.cached_displays.values());
+        self.previous_displays = before;
+
+        tag_primary_shift(&mut events, self.cached_displays.values());
+        sort_events(&mut events);
+        events
+    }
+
+    /// Set why the displays of the events were added or removed.
+    ///
+    /// A display that starts or stops being mirrored disappears or reappears
+    /// on its own, since a mirror set is reported as a single monitor.
+    /// Otherwise, a monitor device notification received since the last
+    /// addition or removal means the monitor was plugged or unplugged.
+    fn tag_change_reasons(&mut self, events: &mut [Event]) {
+        let is_addition_or_removal =
+            |event: &Event| matches!(event, Event::Added { .. } | Event::Removed { .. });
+        if !events.iter().any(is_addition_or_removal) {
+            return;
+        }
+
+        let mirroring_changed = events
+            .iter()
+            .any(|event| matches!(event, Event::Mirrored(_) | Event::UnMirrored(_)));
+        let reason = if mirroring_changed {
+            ChangeReason::ModeChange
+        } else if std::mem::take(&mut self.hotplug_pending) {
+            ChangeReason::Hotplug
+        } else {
+            ChangeReason::Arrangement
+        };
+
+        for event in events.iter_mut() {
+            if let Event::Added { reason: r, .. } | Event::Removed { reason: r, .. } = event {
+                *r = reason;
             }
         }
+    }
+
+    /// Compare the color profiles of the displays with the ones seen last time.
+    fn track_color_profiles(&mut self) -> EventBatch {
+        let mut events = EventBatch::new();
+
+        self.color_profiles
+            .retain(|id, _| self.cached_displays.contains_key(id));
 
-        Ok(events)
+        for (id, display) in &self.cached_displays {
+            let profile = get_color_profile(id.device_name());
+
+            // Displays seen for the first time have no previous profile to compare with.
+            if let Some(before) = self.color_profiles.insert(id.clone(), profile.clone())
+                && before != profile
+            {
+                events.push(Event::ColorProfileChanged {
+                    display: display.clone(),
+                });
+            }
+        }
+
+        sort_events(&mut events);
+        events
     }
 }
 
+/// Write an event to the Application event log, under the `display-config` source.
+///
+/// The source is registered on first use and kept for the rest of the process.
+pub(crate) fn report_event(event: &Event) {
+    // `HANDLE` is not `Send`, so the handle is kept as an integer.
+    static SOURCE: OnceLock<Option<usize>> = OnceLock::new();
+
+    let source = SOURCE.get_or_init(|| {
+        unsafe { RegisterEventSourceW(None, w!("display-config")) }
+            .ok()
+            .map(|source| source.0 as usize)
+    });
+    let Some(source) = *source else {
+        return;
+    };
+
+    let message = HSTRING::from(format!("{event:?}"));
+    _ = unsafe {
+        ReportEventW(
+            HANDLE(source as *mut c_void),
+            EVENTLOG_INFORMATION_TYPE,
+            0,
+            0,
+            None,
+            0,
+            Some(&[PCWSTR(message.as_ptr())]),
+            None,
+        )
+    };
+}
+
 struct ObserverContext {
-    callback: Option<DisplayEventCallback>,
+    dispatcher: EventDispatcher,
     tracker: EventTracker,
+    /// Whether [`WindowsDisplayObserver::run`] is running the message loop.
+    running: bool,
+    /// Whether a stop was requested while the message loop wasn't run by the
+    /// observer, e.g. while it was polled.
+    stop_requested: bool,
 }
 
 /// A Windows-specific display observer that monitors changes to the display configuration.
@@ -355,7 +1345,72 @@ struct ObserverContext {
 pub struct WindowsDisplayObserver {
     hwnd: HWND,
     h_notify: HDEVNOTIFY,
+    thread_id: u32,
+    generation: Arc<AtomicU64>,
     ctx: Arc<Mutex<ObserverContext>>,
+    coordinate_space: CoordinateSpace,
+    /// The handle of the hidden window shared with the stop tokens, cleared
+    /// when the observer is dropped.
+    stop_target: Arc<Mutex<Option<usize>>>,
+}
+
+/// The message posted to the hidden window to quit the message loop.
+const WM_STOP_OBSERVER: u32 = WM_APP;
+
+/// The message posted to the hidden window to set the timer of the removals
+/// held back, which only the thread of the window can do.
+const WM_SCHEDULE_REMOVALS: u32 = WM_APP + 1;
+
+/// The message posted to the hidden window to start or stop the HDMI-CEC
+/// timer, which only the thread of the window can do. `WPARAM` is nonzero to
+/// start it.
+const WM_WATCH_HDMI_CEC: u32 = WM_APP + 2;
+
+/// The ID of the timer that delivers the removals held back for reconnection.
+const RECONNECT_TIMER_ID: usize = 1;
+
+/// The ID of the timer that reconciles the displays switched by HDMI-CEC.
+const HDMI_CEC_TIMER_ID: usize = 2;
+
+/// How often the displays are reconciled while HDMI-CEC changes are watched.
+const HDMI_CEC_RECONCILE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Starts or stops the timer reconciling the displays switched by HDMI-CEC.
+/// This must be called on the thread of the window.
+fn set_hdmi_cec_timer(hwnd: HWND, enabled: bool) -> Result<(), WindowsError> {
+    unsafe {
+        if enabled {
+            let elapse = HDMI_CEC_RECONCILE_INTERVAL.as_millis() as u32;
+            if SetTimer(Some(hwnd), HDMI_CEC_TIMER_ID, elapse, None) == 0 {
+                return Err(WindowsError::from_thread());
+            }
+        } else {
+            // This only fails if the timer wasn't set, which is what's wanted anyway.
+            _ = KillTimer(Some(hwnd), HDMI_CEC_TIMER_ID);
+        }
+    }
+    Ok(())
+}
+
+/// A handle to stop [`WindowsDisplayObserver::run`] from any thread.
+#[derive(Debug, Clone)]
+pub struct WindowsStopToken {
+    // `HWND` is not `Send`, so the handle is kept as an integer. It's `None` once
+    // the observer is dropped, since Windows may reuse the handle for another window.
+    hwnd: Arc<Mutex<Option<usize>>>,
+}
+
+impl WindowsStopToken {
+    /// Post the stop message to the hidden window of the observer, unless the
+    /// observer was dropped.
+    pub fn stop(&self) {
+        // The lock is held while posting, so the window can't be destroyed meanwhile.
+        let hwnd = lock_state(&self.hwnd);
+        if let Some(hwnd) = *hwnd {
+            let hwnd = HWND(hwnd as *mut c_void);
+            _ = unsafe { PostMessageW(Some(hwnd), WM_STOP_OBSERVER, WPARAM(0), LPARAM(0)) };
+        }
+    }
 }
 
 impl WindowsDisplayObserver {
@@ -368,6 +1423,15 @@ impl WindowsDisplayObserver {
     /// Returns a [`WindowsError`] if there is an issue creating the window,
     /// registering for notifications, or collecting initial display information.
     pub fn new() -> Result<Self, WindowsError> {
+        Self::with_coordinate_space(CoordinateSpace::default())
+    }
+
+    /// Creates a new `WindowsDisplayObserver` reporting the geometry of the
+    /// displays in the given coordinate space.
+    ///
+    /// # Errors
+    /// Same as [`WindowsDisplayObserver::new`].
+    pub fn with_coordinate_space(coordinate_space: CoordinateSpace) -> Result<Self, WindowsError> {
         let h_instance = unsafe { GetModuleHandleW(None)? };
         let window_class_name = w!("DisplayMonitorClass");
         let window_class = WNDCLASSW {
@@ -378,13 +1442,22 @@ impl WindowsDisplayObserver {
             ..Default::default()
         };
 
-        unsafe {
-            RegisterClassW(&window_class);
+        if unsafe { RegisterClassW(&window_class) } == 0 {
+            let error = WindowsError::from_thread();
+            // The class is left registered by an observer created earlier in this
+            // process, which is fine to reuse.
+            if error.code() != ERROR_CLASS_ALREADY_EXISTS.to_hresult() {
+                return Err(error);
+            }
         }
 
+        let dispatcher = EventDispatcher::default();
+        let generation = dispatcher.generation();
         let ctx = Arc::new(Mutex::new(ObserverContext {
-            callback: None,
-            tracker: EventTracker::new()?,
+            dispatcher,
+            tracker: EventTracker::new(coordinate_space)?,
+            running: false,
+            stop_requested: false,
         }));
         let state_ptr = Arc::as_ptr(&ctx) as *mut c_void;
 
@@ -429,7 +1502,11 @@ impl WindowsDisplayObserver {
         Ok(Self {
             hwnd,
             h_notify,
+            thread_id: unsafe { GetCurrentThreadId() },
+            generation,
             ctx,
+            coordinate_space,
+            stop_target: Arc::new(Mutex::new(Some(hwnd.0 as usize))),
         })
     }
 
@@ -438,24 +1515,245 @@ impl WindowsDisplayObserver {
     /// The provided callback will receive a [`Event`] enum,
     /// indicating the nature of the display change.
     pub fn set_callback(&self, callback: DisplayEventCallback) {
-        let mut state = self.ctx.lock().unwrap();
-        state.callback = Some(callback);
+        let mut state = lock_state(&self.ctx);
+        state.dispatcher.set_callback(callback);
     }
 
     /// Removes the currently set callback function.
     /// After calling this, no display events will be dispatched.
-    pub fn remove_callback(&self) {
-        let mut state = self.ctx.lock().unwrap();
-        state.callback = None;
+    ///
+    /// Returns `true` if a callback was set.
+    pub fn remove_callback(&self) -> bool {
+        let mut state = lock_state(&self.ctx);
+        state.dispatcher.remove_callback()
+    }
+
+    /// Sets the function to be invoked once per display reconfiguration,
+    /// regardless of how many events it produced.
+    pub fn on_any_change(&self, callback: DisplayChangeCallback) {
+        let mut state = lock_state(&self.ctx);
+        state.dispatcher.set_change_callback(callback);
+    }
+
+    /// Sets the function that transforms each event before it reaches the callback.
+    /// If the mapper returns `None`, the event is not dispatched.
+    pub fn set_event_mapper(&self, mapper: DisplayEventMapper) {
+        let mut state = lock_state(&self.ctx);
+        state.dispatcher.set_mapper(mapper);
+    }
+
+    /// Sets how long a removed display is given to come back before
+    /// [`Event::Removed`] is dispatched, or `None` to dispatch it right away.
+    ///
+    /// If it comes back in time, [`Event::Reconnected`] is dispatched instead of
+    /// both events. The removals held back are delivered by a timer of the
+    /// hidden window, so they need the message loop to be running.
+    pub fn set_reconnect_window(&self, window: Option<Duration>) {
+        let mut state = lock_state(&self.ctx);
+        state.dispatcher.set_reconnect_window(window);
+    }
+
+    /// Sets whether the displays are periodically reconciled to catch the ones
+    /// a TV or an AV receiver drops or adds back over HDMI-CEC.
+    ///
+    /// Powering such equipment on or off may change the display configuration
+    /// without any device notification, and with some drivers without
+    /// `WM_DISPLAYCHANGE` either. The displays are then compared every two
+    /// seconds by a timer of the hidden window, so this needs the message loop
+    /// to be running. Displays added or removed this way are reported with
+    /// [`ChangeReason::Hotplug`].
+    ///
+    /// This can be called from any thread. On another thread than the one
+    /// that created the observer, the timer is only set once the message loop
+    /// handles the request, and a failure then is logged instead of returned.
+    ///
+    /// # Errors
+    /// Returns a [`WindowsError`] if the timer couldn't be set, or the request
+    /// couldn't be posted to the hidden window.
+    pub fn watch_hdmi_cec_changes(&self, enabled: bool) -> Result<(), WindowsError> {
+        if unsafe { GetCurrentThreadId() } == self.thread_id {
+            set_hdmi_cec_timer(self.hwnd, enabled)
+        } else {
+            unsafe {
+                PostMessageW(
+                    Some(self.hwnd),
+                    WM_WATCH_HDMI_CEC,
+                    WPARAM(enabled as usize),
+                    LPARAM(0),
+                )
+            }
+        }
+    }
+
+    /// Sets whether every event is also written to the Application event log,
+    /// under the `display-config` source.
+    ///
+    /// See [`DisplayObserverBuilder::log_to_os`][crate::DisplayObserverBuilder::log_to_os]
+    /// for how to install the source, so that Event Viewer shows the events.
+    pub fn set_log_to_os(&self, enabled: bool) {
+        let mut state = lock_state(&self.ctx);
+        state.dispatcher.set_log_to_os(enabled);
+    }
+
+    /// Holds back the events until [`Self::resume`] is called.
+    pub fn suspend(&self) {
+        let mut state = lock_state(&self.ctx);
+        state.dispatcher.suspend();
+    }
+
+    /// Dispatches the net change of the events held back since [`Self::suspend`].
+    pub fn resume(&self) {
+        let mut state = lock_state(&self.ctx);
+        state.dispatcher.resume();
+    }
+
+    /// Gets the coordinate space of the geometry of the displays.
+    pub fn coordinate_space(&self) -> CoordinateSpace {
+        self.coordinate_space
+    }
+
+    /// Gets the number of reconfigurations that produced events so far.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Gets the generation together with the displays known to the observer,
+    /// sorted by ID.
+    ///
+    /// Both are read under the same lock, so the displays are exactly the
+    /// ones of that generation.
+    pub fn snapshot(&self) -> (u64, Vec<Display>) {
+        let state = lock_state(&self.ctx);
+        let displays = sorted_displays(state.tracker.cached_displays.values());
+
+        (self.generation(), displays)
+    }
+
+    /// Compares the displays with the ones known to the observer right away,
+    /// dispatches the events of the difference and returns them.
+    ///
+    /// The events are dispatched on the calling thread, so the callback and
+    /// the handlers run on it before this returns.
+    pub fn reconcile_blocking(&self) -> Result<Vec<Event>, WindowsError> {
+        let mut ctx = lock_state(&self.ctx);
+        let mut events = ctx.tracker.track_events()?;
+        events.extend(ctx.tracker.track_color_profiles());
+
+        ctx.dispatcher.dispatch(events.clone());
+        if ctx.dispatcher.next_removal_deadline().is_some() {
+            _ = unsafe {
+                PostMessageW(Some(self.hwnd), WM_SCHEDULE_REMOVALS, WPARAM(0), LPARAM(0))
+            };
+        }
+        Ok(events.into_vec())
+    }
+
+    /// Gets the displays before and after the last tracked change, each sorted by ID.
+    pub fn last_snapshot(&self) -> (Vec<Display>, Vec<Display>) {
+        let state = lock_state(&self.ctx);
+        (
+            sorted_displays(state.tracker.previous_displays.values()),
+            sorted_displays(state.tracker.cached_displays.values()),
+        )
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn next_event(&self) -> NextEvent {
+        let mut state = lock_state(&self.ctx);
+        state.dispatcher.next_event()
+    }
+
+    pub(crate) fn set_event_handler(&self, kind: EventKind, handler: DisplayEventCallback) {
+        let mut state = lock_state(&self.ctx);
+        state.dispatcher.set_handler(kind, handler);
+    }
+
+    pub(crate) fn set_event_limit(&self, count: usize, on_reached: Box<dyn FnMut() + Send>) {
+        let mut state = lock_state(&self.ctx);
+        state.dispatcher.set_limit(count, on_reached);
+    }
+
+    pub(crate) fn remove_event_limit(&self) {
+        let mut state = lock_state(&self.ctx);
+        state.dispatcher.remove_limit();
+    }
+
+    /// Removes the currently set event mapper.
+    ///
+    /// Returns `true` if an event mapper was set.
+    pub fn remove_event_mapper(&self) -> bool {
+        let mut state = lock_state(&self.ctx);
+        state.dispatcher.remove_mapper()
+    }
+
+    /// Gets a token that stops [`WindowsDisplayObserver::run`] from any thread.
+    pub fn stop_token(&self) -> WindowsStopToken {
+        WindowsStopToken {
+            hwnd: Arc::clone(&self.stop_target),
+        }
+    }
+
+    /// Processes the pending messages of the observer once without blocking
+    /// and returns the events delivered since the last call.
+    ///
+    /// Once this was called, the events are collected even while the event
+    /// loop of the app dispatches the messages itself, e.g. `WM_DISPLAYCHANGE`
+    /// sent to the hidden window, so none is missed.
+    ///
+    /// Only the messages of the hidden window are removed from the queue, so
+    /// this can be called each frame from the event loop of an app running on
+    /// the same thread. A stop requested with a [`WindowsStopToken`] meanwhile
+    /// doesn't quit that event loop, but makes the next
+    /// [`WindowsDisplayObserver::run`] return right away.
+    ///
+    /// # Panics
+    /// This function must be called on the thread that created the observer,
+    /// otherwise it will panic.
+    pub fn poll(&self) -> Vec<Event> {
+        assert_eq!(
+            unsafe { GetCurrentThreadId() },
+            self.thread_id,
+            "This function must be called on the thread that created the observer"
+        );
+
+        lock_state(&self.ctx).dispatcher.start_polling();
+
+        unsafe {
+            let mut msg = MSG::default();
+            while PeekMessageW(&mut msg, Some(self.hwnd), 0, 0, PM_REMOVE).as_bool() {
+                _ = TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+        }
+
+        lock_state(&self.ctx).dispatcher.take_polled_events()
     }
 
     /// Runs the Windows message loop to start handling display events.
     ///
-    /// This function will block the current thread and dispatch messages.
+    /// This function will block the current thread and dispatch messages
+    /// until it's stopped with a [`WindowsStopToken`].
     ///
     /// # Errors
-    /// Returns a [`WindowsError`] if `GetMessageW` fails.
+    /// Returns a [`WindowsError`] with `ERROR_INVALID_THREAD_ID` if this isn't
+    /// called on the thread that created the observer, since only that thread
+    /// receives the messages of its hidden window.
     pub fn run(&self) -> Result<(), WindowsError> {
+        if unsafe { GetCurrentThreadId() } != self.thread_id {
+            return Err(WindowsError::new(
+                ERROR_INVALID_THREAD_ID.to_hresult(),
+                "The observer must be run on the thread that created it.",
+            ));
+        }
+
+        {
+            let mut ctx = lock_state(&self.ctx);
+            if std::mem::take(&mut ctx.stop_requested) {
+                return Ok(());
+            }
+            ctx.running = true;
+        }
+
         unsafe {
             let mut msg = MSG::default();
             while GetMessageW(&mut msg, None, 0, 0).as_bool() {
@@ -464,12 +1762,15 @@ impl WindowsDisplayObserver {
             }
         }
 
+        lock_state(&self.ctx).running = false;
         Ok(())
     }
 }
 
 impl Drop for WindowsDisplayObserver {
     fn drop(&mut self) {
+        *lock_state(&self.stop_target) = None;
+
         unsafe {
             if !self.h_notify.is_invalid() {
                 _ = UnregisterDeviceNotification(self.h_notify);
@@ -482,12 +1783,43 @@ impl Drop for WindowsDisplayObserver {
 #[inline]
 fn process_window_message(
     msg: u32,
-    _wparam: WPARAM,
+    wparam: WPARAM,
     _lparam: LPARAM,
     ctx: &mut ObserverContext,
-) -> Result<Option<SmallVec<[Event; 10]>>, WindowsError> {
+) -> Result<Option<EventBatch>, WindowsError> {
     Ok(match msg {
-        WM_DISPLAYCHANGE => Some(ctx.tracker.track_events()?),
+        // The monitor device usually arrives or is removed before the display
+        // configuration changes, so remember it for the next change.
+        WM_DEVICECHANGE
+            if matches!(
+                wparam.0 as u32,
+                DBT_DEVICEARRIVAL | DBT_DEVICEREMOVECOMPLETE
+            ) =>
+        {
+            ctx.tracker.hotplug_pending = true;
+            Some(ctx.tracker.track_events()?)
+        }
+        WM_DISPLAYCHANGE => {
+            let mut events = ctx.tracker.track_events()?;
+            events.extend(ctx.tracker.track_color_profiles());
+            // The configuration has settled, so a notification that didn't lead to
+            // an addition or removal was about something else, and must not tag a
+            // later change.
+            ctx.tracker.hotplug_pending = false;
+            Some(events)
+        }
+        // Displays switched over HDMI-CEC come and go like a hotplug, but without
+        // the device notification.
+        WM_TIMER if wparam.0 == HDMI_CEC_TIMER_ID => {
+            let hotplug_pending = std::mem::replace(&mut ctx.tracker.hotplug_pending, true);
+            let events = ctx.tracker.track_events()?;
+            // Keep a notification received earlier if this didn't use it up.
+            ctx.tracker.hotplug_pending &= hotplug_pending;
+            Some(events)
+        }
+        // There's no dedicated notification for color profile changes, so check
+        // them whenever something system-wide that may involve them changes.
+        WM_SYSCOLORCHANGE | WM_SETTINGCHANGE => Some(ctx.tracker.track_color_profiles()),
         _ => None,
     })
 }
@@ -511,14 +1843,281 @@ unsafe extern "system" fn wnd_proc(
         &*(user_data_ptr)
     };
 
-    if let Ok(mut ctx) = ctx.lock()
-        && let Ok(Some(events)) = process_window_message(msg, wparam, lparam, &mut ctx)
-        && let Some(callback) = ctx.callback.as_mut()
-    {
-        for event in events {
-            (callback)(event);
+    let mut ctx = lock_state(ctx);
+
+    if msg == WM_STOP_OBSERVER {
+        // Only quit the message loop run by the observer. When the observer is
+        // polled, the loop belongs to the app.
+        if ctx.running {
+            unsafe { PostQuitMessage(0) };
+        } else {
+            ctx.stop_requested = true;
         }
+        return LRESULT(0);
+    }
+
+    if msg == WM_TIMER && wparam.0 == RECONNECT_TIMER_ID {
+        unsafe { _ = KillTimer(Some(hwnd), RECONNECT_TIMER_ID) };
+        ctx.dispatcher.flush_removals();
+    } else if msg == WM_SCHEDULE_REMOVALS {
+        // The removals were held back on another thread, only the timer is left to set.
+    } else if msg == WM_WATCH_HDMI_CEC {
+        if let Err(error) = set_hdmi_cec_timer(hwnd, wparam.0 != 0) {
+            log::warn!("Failed to watch HDMI-CEC changes: {error}");
+        }
+    } else if let Ok(Some(events)) = process_window_message(msg, wparam, lparam, &mut ctx) {
+        ctx.dispatcher.dispatch(events);
+    } else {
+        return default_window_proc();
+    }
+
+    // Wake up again once the next removal held back is due.
+    if let Some(deadline) = ctx.dispatcher.next_removal_deadline() {
+        let elapse = deadline.saturating_duration_since(Instant::now());
+        unsafe {
+            SetTimer(
+                Some(hwnd),
+                RECONNECT_TIMER_ID,
+                (elapse.as_millis() as u32).max(USER_TIMER_MINIMUM),
+                None,
+            )
+        };
     }
 
     default_window_proc()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn display(name: &str, origin: (i32, i32), size: (u32, u32), is_primary: bool) -> Display {
+        Display {
+            id: WindowsDisplayId::new(name.into()).into(),
+            origin: LogicalPosition::new(origin.0, origin.1),
+            size: LogicalSize::new(size.0, size.1),
+            scale_factor: 1.0,
+            is_primary,
+            is_mirrored: false,
+            mirror_source: None,
+            is_builtin: false,
+            is_virtual: false,
+            rotation: DisplayRotation::default(),
+            refresh_rate: Some(60.0),
+            vrr: None,
+            subpixel: None,
+            input_capabilities: InputCapabilities::default(),
+        }
+    }
+
+    fn state(displays: &[Display]) -> HashMap<WindowsDisplayId, Display> {
+        displays
+            .iter()
+            .map(|display| (display.id.windows_id().clone(), display.clone()))
+            .collect()
+    }
+
+    fn tracker(displays: &[Display]) -> EventTracker {
+        EventTracker::from_state(state(displays), CoordinateSpace::default())
+    }
+
+    #[test]
+    fn measures_a_monitor_left_of_and_above_the_primary() {
+        let rect = RECT {
+            left: -1920,
+            top: -1080,
+            right: 0,
+            bottom: 0,
+        };
+
+        assert_eq!(rect_size(&rect), LogicalSize::new(1920, 1080));
+    }
+
+    #[test]
+    fn measures_an_inverted_rect_as_empty() {
+        let rect = RECT {
+            left: 0,
+            top: 0,
+            right: -1920,
+            bottom: -1080,
+        };
+
+        assert_eq!(rect_size(&rect), LogicalSize::new(0, 0));
+    }
+
+    #[test]
+    fn measures_a_rect_spanning_the_coordinate_range() {
+        let rect = RECT {
+            left: i32::MIN,
+            top: 0,
+            right: i32::MAX,
+            bottom: 1,
+        };
+
+        assert_eq!(rect_size(&rect), LogicalSize::new(u32::MAX, 1));
+    }
+
+    #[test]
+    fn reports_nothing_without_a_change() {
+        let displays = [
+            display(r"\\.\DISPLAY1", (0, 0), (1920, 1080), true),
+            display(r"\\.\DISPLAY2", (1920, 0), (1920, 1080), false),
+        ];
+        let mut tracker = tracker(&displays);
+
+        assert!(tracker.track_state(state(&displays)).is_empty());
+    }
+
+    #[test]
+    fn reports_a_resized_display() {
+        let mut tracker = tracker(&[display(r"\\.\DISPLAY1", (0, 0), (1920, 1080), true)]);
+
+        let events = tracker.track_state(state(&[display(
+            r"\\.\DISPLAY1",
+            (0, 0),
+            (2560, 1440),
+            true,
+        )]));
+
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            &events[0],
+            Event::SizeChanged { before, after, .. }
+                if *before == LogicalSize::new(1920, 1080) && *after == LogicalSize::new(2560, 1440)
+        ));
+    }
+
+    #[test]
+    fn reports_removals_before_additions() {
+        let primary = display(r"\\.\DISPLAY1", (0, 0), (1920, 1080), true);
+        let mut tracker = tracker(&[
+            primary.clone(),
+            display(r"\\.\DISPLAY2", (1920, 0), (1920, 1080), false),
+        ]);
+
+        let events = tracker.track_state(state(&[
+            primary,
+            display(r"\\.\DISPLAY3", (1920, 0), (1920, 1080), false),
+        ]));
+
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            &events[0],
+            Event::Removed { id, reason: ChangeReason::Arrangement }
+                if id.windows_id().device_name() == r"\\.\DISPLAY2"
+        ));
+        assert!(matches!(
+            &events[1],
+            Event::Added { display, reason: ChangeReason::Arrangement }
+                if display.id.windows_id().device_name() == r"\\.\DISPLAY3"
+        ));
+    }
+
+    #[test]
+    fn reports_both_sides_of_a_primary_swap() {
+        let mut tracker = tracker(&[
+            display(r"\\.\DISPLAY1", (0, 0), (1920, 1080), true),
+            display(r"\\.\DISPLAY2", (1920, 0), (1920, 1080), false),
+        ]);
+
+        let events = tracker.track_state(state(&[
+            display(r"\\.\DISPLAY1", (-1920, 0), (1920, 1080), false),
+            display(r"\\.\DISPLAY2", (0, 0), (1920, 1080), true),
+        ]));
+
+        let primary_changes: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::PrimaryChanged {
+                    display,
+                    was_primary,
+                } => Some((display.id.windows_id().device_name(), *was_primary)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            primary_changes,
+            [
+                (OsStr::new(r"\\.\DISPLAY1"), true),
+                (OsStr::new(r"\\.\DISPLAY2"), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_one_primary_display_when_mirroring() {
+        let mut tracker = tracker(&[
+            display(r"\\.\DISPLAY1", (0, 0), (1920, 1080), true),
+            display(r"\\.\DISPLAY2", (1920, 0), (1920, 1080), false),
+        ]);
+
+        // A mirror set is reported as a single monitor, the primary one.
+        let mut mirrored = display(r"\\.\DISPLAY1", (0, 0), (1920, 1080), true);
+        mirrored.is_mirrored = true;
+        let events = tracker.track_state(state(&[mirrored]));
+
+        let primaries = tracker
+            .cached_displays
+            .values()
+            .filter(|display| display.is_primary)
+            .count();
+        assert_eq!(primaries, 1);
+
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            &events[0],
+            Event::Removed { id, reason: ChangeReason::ModeChange }
+                if id.windows_id().device_name() == r"\\.\DISPLAY2"
+        ));
+        assert!(matches!(&events[1], Event::Mirrored(display) if display.is_primary));
+    }
+
+    #[test]
+    fn tags_the_additions_after_a_device_notification_as_hotplug() {
+        let primary = display(r"\\.\DISPLAY1", (0, 0), (1920, 1080), true);
+        let mut tracker = tracker(std::slice::from_ref(&primary));
+        tracker.hotplug_pending = true;
+
+        let events = tracker.track_state(state(&[
+            primary.clone(),
+            display(r"\\.\DISPLAY2", (1920, 0), (1920, 1080), false),
+        ]));
+
+        assert!(matches!(
+            &events[..],
+            [Event::Added {
+                reason: ChangeReason::Hotplug,
+                ..
+            }]
+        ));
+        assert!(!tracker.hotplug_pending);
+
+        // Without a notification, the next change is a change of the arrangement.
+        let events = tracker.track_state(state(&[primary]));
+        assert!(matches!(
+            &events[..],
+            [Event::Removed {
+                reason: ChangeReason::Arrangement,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn keeps_the_last_sane_state_of_a_display() {
+        let settled = display(r"\\.\DISPLAY1", (0, 0), (1920, 1080), true);
+        let mut tracker = tracker(std::slice::from_ref(&settled));
+
+        let events = tracker.track_state(state(&[
+            display(r"\\.\DISPLAY1", (0, 0), (0, 0), true),
+            display(r"\\.\DISPLAY2", (1920, 0), (0, 0), false),
+        ]));
+
+        assert!(events.is_empty());
+        assert_eq!(tracker.cached_displays.len(), 1);
+        assert_eq!(
+            tracker.cached_displays[settled.id.windows_id()].size,
+            settled.size
+        );
+    }
+}